@@ -0,0 +1,349 @@
+use crate::browser_control::actions::document_filename_problem;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// The element an action works on: its id in the element list (the number drawn on the
+/// screenshot) or a CSS selector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ElementTarget {
+    Id(usize),
+    Selector(String),
+}
+
+impl ElementTarget {
+    /// From the `element` and `selector` parameters of an action, exactly one of which
+    /// must be given.
+    fn from_params(element: Option<usize>, selector: Option<String>) -> Result<Self, String> {
+        match (element, selector) {
+            (Some(id), None) => Ok(ElementTarget::Id(id)),
+            (None, Some(selector)) => Ok(ElementTarget::Selector(selector)),
+            (Some(_), Some(_)) => Err("give either `element` or `selector`, not both".to_string()),
+            (None, None) => Err("an `element` id or a `selector` is required".to_string()),
+        }
+    }
+
+    /// Why the target can't refer to an element, if it can't.
+    fn problem(&self) -> Option<&'static str> {
+        match self {
+            ElementTarget::Id(0) => Some("element ids start at 1"),
+            ElementTarget::Selector(selector) if selector.trim().is_empty() => {
+                Some("`selector` must not be empty")
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ElementTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElementTarget::Id(id) => write!(f, "element {}", id),
+            ElementTarget::Selector(selector) => write!(f, "selector {}", selector),
+        }
+    }
+}
+
+/// A single form field to fill: the element (id or CSS selector) and the value to type
+/// into it.
+///
+/// Accepts the `["selector", "value"]` and `[3, "value"]` pair shapes as well as the
+/// `{"element": 3, "value": "..."}` and `{"selector": "...", "value": "..."}` object
+/// shapes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "FormFieldRepr")]
+pub struct FormField {
+    pub target: ElementTarget,
+    pub value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FormFieldRepr {
+    Pair(ElementTarget, String),
+    Object {
+        #[serde(default)]
+        element: Option<usize>,
+        #[serde(default)]
+        selector: Option<String>,
+        value: String,
+    },
+}
+
+impl TryFrom<FormFieldRepr> for FormField {
+    type Error = String;
+
+    fn try_from(repr: FormFieldRepr) -> Result<Self, Self::Error> {
+        match repr {
+            FormFieldRepr::Pair(target, value) => Ok(FormField { target, value }),
+            FormFieldRepr::Object {
+                element,
+                selector,
+                value,
+            } => Ok(FormField {
+                target: ElementTarget::from_params(element, selector)?,
+                value,
+            }),
+        }
+    }
+}
+
+/// Output formats accepted by `create_document` and `generate_document`.
+pub const DOCUMENT_FORMATS: &[&str] = &["markdown", "md", "text", "txt", "json", "html"];
+
+/// The `next_action` chosen by the task agent.
+///
+/// Serialized in serde's externally tagged form, which is exactly the shape the task
+/// prompt asks for: `{"go_to_url": {"url": "..."}}`. Unknown parameters are rejected so
+/// that misspelled fields surface as errors instead of being silently ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Action {
+    GoToUrl {
+        url: String,
+    },
+    ExtractContent {},
+    /// Either `element` (an id from the element list) or `selector` is given; see
+    /// [`Action::click_target`].
+    ClickElement {
+        #[serde(default)]
+        element: Option<usize>,
+        #[serde(default)]
+        selector: Option<String>,
+    },
+    FillForm {
+        #[serde(alias = "data")]
+        form_data: Vec<FormField>,
+    },
+    FillFormWithUserInputCredentials {
+        #[serde(alias = "data")]
+        form_data: Vec<ElementTarget>,
+    },
+    SearchQuery {
+        query: String,
+    },
+    GoBack {},
+    CreateDocument {
+        filename: String,
+        content: String,
+        format: String,
+    },
+    GenerateDocument {
+        task_description: String,
+        filename: String,
+        format: String,
+    },
+    FinalAnswer {
+        #[serde(default)]
+        answer: Option<String>,
+    },
+    Done {},
+    Stop {
+        #[serde(default)]
+        final_answer: Option<String>,
+    },
+}
+
+/// Why a task agent response could not be turned into an [`Action`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionError {
+    /// The response was not valid JSON at all.
+    InvalidJson(String),
+    /// The response has no `next_action` field.
+    MissingAction,
+    /// `next_action` did not match any known action or its parameter shape.
+    UnknownAction(String),
+    /// The action was recognised but a parameter value is unusable.
+    InvalidParameters { action: String, reason: String },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::InvalidJson(e) => write!(f, "response is not valid JSON: {}", e),
+            ActionError::MissingAction => write!(f, "response has no `next_action` field"),
+            ActionError::UnknownAction(e) => write!(f, "invalid `next_action`: {}", e),
+            ActionError::InvalidParameters { action, reason } => {
+                write!(f, "invalid parameters for `{}`: {}", action, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+impl Action {
+    /// Snake-case name of the action, as used in the prompt.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::GoToUrl { .. } => "go_to_url",
+            Action::ExtractContent {} => "extract_content",
+            Action::ClickElement { .. } => "click_element",
+            Action::FillForm { .. } => "fill_form",
+            Action::FillFormWithUserInputCredentials { .. } => {
+                "fill_form_with_user_input_credentials"
+            }
+            Action::SearchQuery { .. } => "search_query",
+            Action::GoBack {} => "go_back",
+            Action::CreateDocument { .. } => "create_document",
+            Action::GenerateDocument { .. } => "generate_document",
+            Action::FinalAnswer { .. } => "final_answer",
+            Action::Done {} => "done",
+            Action::Stop { .. } => "stop",
+        }
+    }
+
+    /// The element a `click_element` action targets.
+    pub fn click_target(&self) -> Result<ElementTarget, String> {
+        match self {
+            Action::ClickElement { element, selector } => {
+                ElementTarget::from_params(*element, selector.clone())
+            }
+            _ => Err(format!("`{}` does not target an element", self.name())),
+        }
+    }
+
+    /// Parses a `next_action` value and validates its parameters.
+    pub fn from_value(value: &Value) -> Result<Action, ActionError> {
+        let action: Action = serde_json::from_value(value.clone())
+            .map_err(|e| ActionError::UnknownAction(e.to_string()))?;
+        action.validate()?;
+        Ok(action)
+    }
+
+    /// Checks parameter values that deserialization alone cannot catch.
+    pub fn validate(&self) -> Result<(), ActionError> {
+        let invalid = |reason: &str| ActionError::InvalidParameters {
+            action: self.name().to_string(),
+            reason: reason.to_string(),
+        };
+
+        match self {
+            Action::GoToUrl { url } => {
+                if url.trim().is_empty() {
+                    return Err(invalid("`url` must not be empty"));
+                }
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(invalid("`url` must start with http:// or https://"));
+                }
+            }
+            Action::ClickElement { .. } => {
+                let target = self.click_target().map_err(|reason| invalid(&reason))?;
+                if let Some(problem) = target.problem() {
+                    return Err(invalid(problem));
+                }
+            }
+            Action::SearchQuery { query } if query.trim().is_empty() => {
+                return Err(invalid("`query` must not be empty"));
+            }
+            Action::FillForm { form_data } => {
+                if form_data.is_empty() {
+                    return Err(invalid("`form_data` must contain at least one field"));
+                }
+                if let Some(problem) = form_data.iter().find_map(|field| field.target.problem()) {
+                    return Err(invalid(problem));
+                }
+            }
+            Action::FillFormWithUserInputCredentials { form_data } => {
+                if form_data.is_empty() {
+                    return Err(invalid(
+                        "`form_data` must be a non-empty list of element ids or selectors",
+                    ));
+                }
+                if let Some(problem) = form_data.iter().find_map(ElementTarget::problem) {
+                    return Err(invalid(problem));
+                }
+            }
+            Action::CreateDocument {
+                filename, format, ..
+            }
+            | Action::GenerateDocument {
+                filename, format, ..
+            } => {
+                if filename.trim().is_empty() {
+                    return Err(invalid("`filename` must not be empty"));
+                }
+                if let Some(problem) = document_filename_problem(filename) {
+                    return Err(invalid(problem));
+                }
+                if !DOCUMENT_FORMATS.contains(&format.to_lowercase().as_str()) {
+                    return Err(invalid(&format!(
+                        "`format` must be one of {}",
+                        DOCUMENT_FORMATS.join(", ")
+                    )));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// An action chosen by the model: the `next_action` key and its parameter object.
+///
+/// Unlike [`Action`], a call may name any action registered in the
+/// [`ActionRegistry`](crate::agent::registry::ActionRegistry), including custom ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionCall {
+    pub name: String,
+    pub params: Value,
+}
+
+impl ActionCall {
+    /// Splits a `{"name": {params}}` object into an [`ActionCall`].
+    pub fn from_value(value: &Value) -> Result<ActionCall, ActionError> {
+        let object = value.as_object().ok_or_else(|| {
+            ActionError::UnknownAction("`next_action` must be a JSON object".to_string())
+        })?;
+        if object.len() != 1 {
+            return Err(ActionError::UnknownAction(format!(
+                "`next_action` must contain exactly one action, found {}",
+                object.len()
+            )));
+        }
+        let (name, params) = object.iter().next().unwrap();
+        let params = if params.is_null() {
+            Value::Object(Default::default())
+        } else {
+            params.clone()
+        };
+        Ok(ActionCall {
+            name: name.clone(),
+            params,
+        })
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::json!({ self.name.clone(): self.params })
+    }
+}
+
+/// Extracts the `next_action` from a raw task agent response.
+///
+/// A response carrying a top-level `"done": true` is treated as a `stop` call with its
+/// `final_answer`, matching the older response shape. Parameters are validated later
+/// against the registry.
+pub fn parse_next_action(response: &str) -> Result<ActionCall, ActionError> {
+    let json: Value =
+        serde_json::from_str(response).map_err(|e| ActionError::InvalidJson(e.to_string()))?;
+
+    if json["done"].as_bool() == Some(true) {
+        let mut params = serde_json::Map::new();
+        if let Some(answer) = json["final_answer"].as_str() {
+            params.insert(
+                "final_answer".to_string(),
+                Value::String(answer.to_string()),
+            );
+        }
+        return Ok(ActionCall {
+            name: "stop".to_string(),
+            params: Value::Object(params),
+        });
+    }
+
+    match json.get("next_action") {
+        Some(value) => ActionCall::from_value(value),
+        None => Err(ActionError::MissingAction),
+    }
+}
@@ -1,21 +1,25 @@
-use crate::agent::executor::execute_task;
+use crate::agent::action::{ActionCall, ActionError, parse_next_action};
+use crate::agent::history::{AgentHistory, HistoryEntry, now_millis};
+use crate::agent::registry::{ActionContext, ActionRegistry};
 use crate::agent::task::task_agent;
-use crate::agent::task::{add_task_to_history, get_task_history};
-use project_oculus::browser_control::interactive_elements::get_interactive_elements_in_hashmap;
+use crate::browser_control::accessibility::{
+    ObservationConfig, ObservationMode, accessibility_tree, render_tree,
+};
+use crate::browser_control::backend::{BrowserBackend, RESOLVE_LOCATOR_JS};
+use crate::browser_control::interactive_elements::{InteractiveElement, get_interactive_elements};
+use crate::llm::image::{ImagePart, VisionConfig};
+use crate::memory::memory_manager::MemoryManager;
+use crate::memory::types::{MemoryKind, MemoryQuery, MemoryRecord};
+use crate::session::recorder::SessionRecorder;
+use crate::session::types::SessionEvent;
+use crate::trace::{AgentStepTrace, TraceEvent, TraceWriter};
+use crate::utils::fnv1a;
 use serde_json::Value;
 use std::collections::HashSet;
-use std::fs;
-use std::path::Path;
-use thirtyfour::WebDriver;
-// Define the orchestrator trait that agents can call
-
-#[derive(Debug, Clone)]
-pub struct TaskRecord {
-    pub step: usize,
-    pub action: String,
-    pub result: String,
-    pub timestamp: std::time::SystemTime,
-}
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{Instrument, Span, debug, error, field, info, info_span, instrument, warn};
 
 pub struct AIAgent {
     id: String,
@@ -25,13 +29,45 @@ pub struct AIAgent {
     role: String,
     backstory: String,
     context: String,
-    driver: WebDriver,
-    task_history: Vec<TaskRecord>,
+    browser: Arc<dyn BrowserBackend>,
+    history: AgentHistory,
     extracted_urls: HashSet<String>,
-    last_screenshot: Option<String>,
+    /// Elements of the current page, numbered as on the annotated screenshot.
+    elements: Vec<InteractiveElement>,
+    /// Annotated screenshot of the current page, attached to the prompt.
+    last_screenshot: Option<ImagePart>,
+    vision: VisionConfig,
+    observation: ObservationConfig,
+    /// Content read by the last action, for the step trace.
+    last_extracted: Option<String>,
+    screenshot_dir: PathBuf,
+    recorder: Option<Arc<SessionRecorder>>,
+    actions: Arc<ActionRegistry>,
+    max_steps: usize,
+    settle_delay: Duration,
+    step_delay: Duration,
+    output_dir: PathBuf,
+    memory: Option<Arc<MemoryManager>>,
+    trace: Option<Arc<TraceWriter>>,
 }
 
+/// Default for [`AIAgent::with_max_steps`].
+pub const DEFAULT_MAX_STEPS: usize = 25;
+
+/// Default for [`AIAgent::with_settle_delay`].
+pub const DEFAULT_SETTLE_DELAY: Duration = Duration::from_millis(1000);
+
+/// Default for [`AIAgent::with_step_delay`].
+pub const DEFAULT_STEP_DELAY: Duration = Duration::from_millis(500);
+
+/// How many memories are included in each task prompt.
+const MEMORIES_IN_PROMPT: usize = 8;
+
+/// How many history entries are included in each task prompt.
+const HISTORY_IN_PROMPT: usize = 5;
+
 impl AIAgent {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: String,
         goal: String,
@@ -40,9 +76,10 @@ impl AIAgent {
         role: String,
         backstory: String,
         context: String,
-        driver: WebDriver,
+        browser: Arc<dyn BrowserBackend>,
     ) -> Self {
         AIAgent {
+            history: AgentHistory::new(id.clone()),
             id,
             goal,
             description,
@@ -50,53 +87,140 @@ impl AIAgent {
             role,
             backstory,
             context,
-            driver,
-            task_history: Vec::new(),
+            browser,
             extracted_urls: HashSet::new(),
+            elements: Vec::new(),
             last_screenshot: None,
+            vision: VisionConfig::default(),
+            observation: ObservationConfig::default(),
+            last_extracted: None,
+            screenshot_dir: PathBuf::from("images"),
+            recorder: None,
+            actions: Arc::new(ActionRegistry::with_builtins()),
+            max_steps: DEFAULT_MAX_STEPS,
+            settle_delay: DEFAULT_SETTLE_DELAY,
+            step_delay: DEFAULT_STEP_DELAY,
+            output_dir: PathBuf::from("documents"),
+            memory: None,
+            trace: None,
         }
     }
 
-    pub fn get_task_history(&self) -> &Vec<TaskRecord> {
-        &self.task_history
+    /// Replaces the built-in action set, e.g. with a registry extended by custom handlers.
+    pub fn with_actions(mut self, actions: Arc<ActionRegistry>) -> Self {
+        self.actions = actions;
+        self
     }
 
-    pub fn clear_task_history(&mut self) {
-        self.task_history.clear();
+    /// Maximum number of actions before the agent gives up and reports a failure.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
     }
 
-    pub fn has_extracted_content_from_url(&self, url: &str) -> bool {
-        self.extracted_urls.contains(url)
+    /// How long to let the page settle before it is observed at the start of each step.
+    pub fn with_settle_delay(mut self, settle_delay: Duration) -> Self {
+        self.settle_delay = settle_delay;
+        self
     }
 
-    pub fn mark_url_as_extracted(&mut self, url: String) {
-        self.extracted_urls.insert(url);
+    /// Pause after each step before the next one starts.
+    pub fn with_step_delay(mut self, step_delay: Duration) -> Self {
+        self.step_delay = step_delay;
+        self
     }
 
-    fn add_task_record(&mut self, step: usize, action: String, result: String) {
-        let record = TaskRecord {
-            step,
-            action,
-            result,
-            timestamp: std::time::SystemTime::now(),
-        };
-        self.task_history.push(record);
+    /// Directory for documents created by the agent's actions.
+    pub fn with_output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
     }
 
-    // Helper to add to external task history
-    async fn try_add_to_external_history<T: std::fmt::Display>(&self, entry: T) {
-        if let Err(e) = add_task_to_history(entry.to_string()).await {
-            eprintln!("Failed to add task to history: {}", e);
+    /// Records into (and reads from) a run's shared history instead of a private one.
+    pub fn with_history(mut self, history: AgentHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Long-term memory consulted when building prompts and updated with what the agent
+    /// learns, visits, finds and fails at.
+    pub fn with_memory(mut self, memory: Arc<MemoryManager>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Where the annotated step screenshots are saved, as `<agent id>_step_<n>.png`.
+    pub fn with_screenshot_dir(mut self, screenshot_dir: impl Into<PathBuf>) -> Self {
+        self.screenshot_dir = screenshot_dir.into();
+        self
+    }
+
+    /// Whether the annotated screenshot is attached to prompts, and at what size.
+    pub fn with_vision(mut self, vision: VisionConfig) -> Self {
+        self.vision = vision;
+        self
+    }
+
+    /// Whether the page is shown to the model as the element list or as its accessibility
+    /// tree.
+    pub fn with_observation(mut self, observation: ObservationConfig) -> Self {
+        self.observation = observation;
+        self
+    }
+
+    /// Emits an [`AgentStepTrace`] for every iteration of the agent loop.
+    pub fn with_trace(mut self, trace: Arc<TraceWriter>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Records this agent's observations, actions and screenshots into a session archive.
+    pub fn with_recorder(mut self, recorder: Arc<SessionRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    fn record_event(&self, event: SessionEvent) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(&event);
         }
     }
 
-    // Helper to get last history entry or fallback
-    async fn get_last_history_or(&self, fallback: &str) -> String {
-        get_task_history()
-            .await
-            .unwrap_or_default()
-            .last()
-            .cloned()
+    async fn finish_step_trace(&self, mut step_trace: AgentStepTrace) {
+        if let Some(trace) = &self.trace {
+            step_trace.url_after = self.browser.current_url().await.ok();
+            step_trace.finished_at = now_millis();
+            trace.emit(TraceEvent::AgentStep(Box::new(step_trace)));
+        }
+    }
+
+    fn remember(&self, kind: MemoryKind, content: &str, url: &str) {
+        if let Some(memory) = &self.memory {
+            let url = Some(url).filter(|url| url.contains("://"));
+            if let Some(record) = memory.remember(kind, content, url, &self.id) {
+                debug!("Remembered {}: {}", record.kind.label(), record.content);
+            }
+        }
+    }
+
+    /// This agent's own history entries.
+    pub fn get_task_history(&self) -> Vec<HistoryEntry> {
+        self.history.own()
+    }
+
+    pub fn has_extracted_content_from_url(&self, url: &str) -> bool {
+        self.extracted_urls.contains(url)
+    }
+
+    pub fn mark_url_as_extracted(&mut self, url: String) {
+        self.extracted_urls.insert(url);
+    }
+
+    // Helper to get this agent's last history entry or fallback
+    fn get_last_history_or(&self, fallback: &str) -> String {
+        self.history
+            .last_own()
+            .map(|entry| entry.render())
             .unwrap_or_else(|| fallback.to_string())
     }
 
@@ -105,13 +229,17 @@ impl AIAgent {
     }
 
     async fn _invoke_loop(&mut self) -> Result<String, Box<dyn std::error::Error>> {
-        println!(
+        info!(
             "Agent {} starting work towards goal: {}",
             self.id, self.goal
         );
 
         let mut final_answer: String = String::new();
-        let max_steps = 25;
+        // Set when the agent stops without reaching its goal; reported as an error so the
+        // orchestrator can re-plan instead of passing a non-answer downstream.
+        let mut failure: Option<String> = None;
+        let mut finished = false;
+        let max_steps = self.max_steps;
         let mut current_step = 0;
         let mut completed_tasks: Vec<String> = Vec::new();
         let mut plan_steps: Vec<String> = self
@@ -128,210 +256,340 @@ impl AIAgent {
 
         while current_step < max_steps && !plan_steps.is_empty() {
             current_step += 1;
-            println!("\n--- Agent Step {} ---", current_step);
+            let step_span = info_span!(
+                "agent_step",
+                agent_id = %self.id,
+                step = current_step,
+                goal = field::Empty
+            );
+            // The body runs as one future inside the span; `true` stops the loop.
+            let stop = async {
+                let started_at = now_millis();
+
+                // 1. Get the current goal/step
+                let current_goal = if plan_steps.len() == 1 {
+                    plan_steps[0].clone()
+                } else {
+                    plan_steps.remove(0)
+                };
+                Span::current().record("goal", current_goal.as_str());
+                debug!("Current goal: {}", current_goal);
+
+                // 2. Gather state: interactive elements, history, current URL
+                self.elements = match get_interactive_elements(self.browser.as_ref()).await {
+                    Ok(elements) => elements,
+                    Err(e) => {
+                        warn!("Failed to get interactive elements: {}. Continuing.", e);
+                        Vec::new()
+                    }
+                };
 
-            // 1. Get the current goal/step
-            let current_goal = if plan_steps.len() == 1 {
-                plan_steps[0].clone()
-            } else {
-                plan_steps.remove(0)
-            };
-            println!("Current goal: {}", current_goal);
-
-            // 2. Gather state: interactive elements, history, current URL
-            let interactive_elements = match get_interactive_elements_in_hashmap(&self.driver).await
-            {
-                Ok(elements) => elements,
-                Err(e) => {
-                    eprintln!("Failed to get interactive elements: {}. Continuing.", e);
-                    Default::default()
-                }
-            };
+                let page_str = self.describe_page().await;
 
-            let interactive_elements_str = if interactive_elements.is_empty() {
-                String::from("No interactive elements found.")
-            } else {
-                interactive_elements
-                    .iter()
-                    .map(|(k, v)| format!("{}: {:?}", k, v))
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            };
+                let recent_history = self.history.render_recent(HISTORY_IN_PROMPT);
+                let history_str = if recent_history.is_empty() {
+                    String::from("No previous actions taken.")
+                } else {
+                    format!(
+                        "Task History (most recent last):\n{}",
+                        recent_history.join("\n")
+                    )
+                };
 
-            let task_history = get_task_history().await.unwrap_or_default();
-            let history_str = if task_history.is_empty() {
-                String::from("No previous actions taken.")
-            } else {
-                format!(
-                    "Task History (most recent last):\n{}",
-                    task_history
+                let current_url = match self.browser.current_url().await {
+                    Ok(url) => url,
+                    Err(e) => {
+                        warn!("Failed to get current URL: {}", e);
+                        String::from("Unknown (error getting URL)")
+                    }
+                };
+                tokio::time::sleep(self.settle_delay).await;
+
+                self.record_event(SessionEvent::Observation {
+                    agent_id: self.id.clone(),
+                    step: current_step,
+                    url: current_url.clone(),
+                    interactive_elements: self
+                        .elements
                         .iter()
-                        .rev()
-                        .take(5)
-                        .rev()
-                        .cloned()
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                )
-            };
-
-            let current_url = match self.driver.current_url().await {
-                Ok(url) => url.to_string(),
-                Err(e) => {
-                    eprintln!("Failed to get current URL: {}", e);
-                    String::from("Unknown (error getting URL)")
+                        .map(|element| (element.key.clone(), element.to_string()))
+                        .collect(),
+                });
+
+                // Number the elements on a screenshot with the ids used in the element list
+                let screenshot_path = self
+                    .screenshot_dir
+                    .join(format!("{}_step_{}.png", file_safe(&self.id), current_step))
+                    .to_string_lossy()
+                    .into_owned();
+                let mut screenshot = None;
+                self.last_screenshot = None;
+                if !self.elements.is_empty() {
+                    match self
+                        .highlight_and_screenshot_interactive_elements(&screenshot_path)
+                        .await
+                    {
+                        Ok(png) => {
+                            if self.vision.enabled {
+                                match self.vision.encode_screenshot(&png) {
+                                    Ok(image) => self.last_screenshot = Some(image),
+                                    Err(e) => warn!("Failed to encode screenshot for the model: {}", e),
+                                }
+                            }
+                            screenshot = Some(screenshot_path.clone());
+                            if let Some(recorder) = &self.recorder {
+                                recorder.record_screenshot(
+                                    &self.id,
+                                    current_step,
+                                    std::path::Path::new(&screenshot_path),
+                                );
+                            }
+                        }
+                        Err(e) => warn!("Failed to highlight elements or take screenshot: {}", e),
+                    }
                 }
-            };
-            tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-
-            // 3. Create prompt for task agent
-            let task_agent_input = self.gen_prompt(
-                current_goal.clone(),
-                current_url,
-                history_str,
-                interactive_elements_str,
-            );
-
-            println!(
-                "Input for Task Agent:\n{}\n------------------------",
-                task_agent_input
-            );
 
-            // 4. Get AI response from task agent
-            let task_agent_result = task_agent(task_agent_input.clone()).await;
-
-            match task_agent_result {
-                Ok(ai_response) => {
-                    println!("Task Agent AI Response: {}", ai_response);
-                    self.try_add_to_external_history(format!(
-                        "Step {}: Task Agent decided: {}",
-                        current_step, ai_response
-                    ))
-                    .await;
-
-                    // Parse the AI response to determine the next action
-                    let next_action: String = match serde_json::from_str::<Value>(&ai_response) {
-                        Ok(json_value) => {
-                            if let Some(action) = json_value.get("next_action") {
-                                action.to_string().trim_matches('"').to_string()
-                            } else {
-                                eprintln!("No 'next_action' found in AI response.");
-                                "CONTINUE".to_string()
-                            }
+                // 3. Create prompt for task agent
+                let memories = self.relevant_memories(&current_goal, &current_url).await;
+                let task_agent_input = self.gen_prompt(
+                    current_goal.clone(),
+                    current_url.clone(),
+                    history_str,
+                    page_str,
+                    &memories,
+                );
+
+                debug!(
+                    "Input for Task Agent:\n{}\n------------------------",
+                    task_agent_input
+                );
+
+                // 4. Get AI response from task agent
+                let task_prompt = self.actions.task_prompt();
+                let mut step_trace = AgentStepTrace {
+                    agent_id: self.id.clone(),
+                    step: current_step,
+                    goal: current_goal.clone(),
+                    prompt_hash: format!(
+                        "{:016x}",
+                        fnv1a(format!("{}{}", task_prompt, task_agent_input).as_bytes())
+                    ),
+                    prompt_chars: task_prompt.len() + task_agent_input.len(),
+                    url_before: current_url.clone(),
+                    screenshot,
+                    started_at,
+                    ..Default::default()
+                };
+                let llm_started = std::time::Instant::now();
+                let task_agent_result = task_agent(
+                    task_agent_input.clone(),
+                    &task_prompt,
+                    self.last_screenshot.as_slice(),
+                )
+                .await;
+                step_trace.llm_latency_ms = Some(llm_started.elapsed().as_millis() as u64);
+
+                match task_agent_result {
+                    Ok(response) => {
+                        step_trace.usage = response.usage;
+                        let ai_response = response.text;
+                        debug!("Task Agent AI Response: {}", ai_response);
+
+                        // Parse the AI response to determine the next action
+                        let next_action = parse_next_action(&ai_response);
+                        match &next_action {
+                            Ok(call) => step_trace.action = Some(call.to_value()),
+                            Err(e) => step_trace.action_error = Some(e.to_string()),
                         }
-                        Err(e) => {
-                            eprintln!("Error parsing AI response JSON: {}", e);
-                            "CONTINUE".to_string()
+                        for note in remember_notes(&ai_response) {
+                            self.remember(MemoryKind::Fact, &note, &current_url);
                         }
-                    };
-
-                    // 5. Execute the action
-                    match self.execute_action(ai_response.clone(), next_action).await {
-                        Ok(result) => {
-                            println!("Execution Result: {}", result);
-                            self.add_task_record(
-                                current_step,
-                                current_goal.clone(),
-                                result.clone(),
-                            );
-                            self.try_add_to_external_history(format!(
-                                "Step {}: Execution result: {}",
-                                current_step, result
-                            ))
-                            .await;
-
-                            if result.starts_with("FINAL_ANSWER:") {
-                                final_answer =
-                                    result.replace("FINAL_ANSWER: ", "").trim().to_string();
-                                println!("Final answer received: {}", final_answer);
-                                completed_tasks.push(current_goal.clone());
-                                break;
-                            } else if result.trim_matches('"').eq_ignore_ascii_case("AGENT_DONE") {
-                                println!("Task marked as complete by executor.");
-                                final_answer = self.get_last_history_or("Task completed.").await;
-                                completed_tasks.push(current_goal.clone());
-                                if plan_steps.len() == 1 || plan_steps.is_empty() {
-                                    break;
+
+                        // 5. Execute the action; invalid actions are reported back to the model
+                        let execution = match &next_action {
+                            Ok(action) => self.execute_action(action).await,
+                            Err(ActionError::InvalidJson(e)) => {
+                                warn!("Error parsing AI response JSON: {}", e);
+                                Ok("ERROR_PARSING_JSON".to_string())
+                            }
+                            Err(e) => {
+                                warn!("Invalid action in AI response: {}", e);
+                                Ok(format!("ERROR_INVALID_ACTION: {}", e))
+                            }
+                        };
+
+                        match execution {
+                            Ok(result) => {
+                                info!("Execution Result: {}", result);
+                                step_trace.result = Some(result.clone());
+                                step_trace.extracted =
+                                    self.last_extracted.take().map(truncate_extracted);
+                                self.record_event(SessionEvent::Action {
+                                    agent_id: self.id.clone(),
+                                    step: current_step,
+                                    action: match &next_action {
+                                        Ok(call) => call.to_value(),
+                                        Err(_) => Value::Null,
+                                    },
+                                    result: result.clone(),
+                                });
+                                let note = if result.contains("ERROR_PARSING_JSON") {
+                                    Some(format!(
+                                        "Your response was not valid JSON. Previous response: {}",
+                                        ai_response
+                                    ))
+                                } else if result.starts_with("ERROR_INVALID_ACTION") {
+                                    Some("The chosen action was rejected. Fix the action name and parameters to match the documented format.".to_string())
+                                } else if result
+                                    .trim_matches('"')
+                                    .eq_ignore_ascii_case("CONTENT_ALREADY_EXTRACTED")
+                                {
+                                    Some("Content already extracted from current URL".to_string())
+                                } else {
+                                    None
+                                };
+                                self.history.record(HistoryEntry {
+                                    agent_id: self.id.clone(),
+                                    step: current_step,
+                                    goal: current_goal.clone(),
+                                    action: next_action.as_ref().ok().map(|call| call.name.clone()),
+                                    params: next_action.as_ref().ok().map(|call| call.params.clone()),
+                                    result: result.clone(),
+                                    note,
+                                    url: Some(current_url.clone()),
+                                    started_at,
+                                    finished_at: now_millis(),
+                                });
+
+                                if result.starts_with("FINAL_ANSWER:") {
+                                    final_answer =
+                                        result.replace("FINAL_ANSWER: ", "").trim().to_string();
+                                    info!("Final answer received: {}", final_answer);
+                                    self.remember(
+                                        MemoryKind::Finding,
+                                        &format!("{}: {}", current_goal, final_answer),
+                                        &current_url,
+                                    );
+                                    completed_tasks.push(current_goal.clone());
+                                    finished = true;
+                                    self.finish_step_trace(step_trace).await;
+                                    return true;
+                                } else if result.trim_matches('"').eq_ignore_ascii_case("AGENT_DONE")
+                                    || result.trim_matches('"').eq_ignore_ascii_case("TASK_COMPLETE")
+                                {
+                                    info!("Task marked as complete by executor.");
+                                    final_answer = self.get_last_history_or("Task completed.");
+                                    completed_tasks.push(current_goal.clone());
+                                    // `stop` without an answer ends the agent whatever steps remain
+                                    if plan_steps.len() <= 1
+                                        || result.trim_matches('"').eq_ignore_ascii_case("TASK_COMPLETE")
+                                    {
+                                        finished = true;
+                                        self.finish_step_trace(step_trace).await;
+                                        return true;
+                                    }
+                                } else if result
+                                    .trim_matches('"')
+                                    .eq_ignore_ascii_case("CONTENT_ALREADY_EXTRACTED")
+                                {
+                                    info!(
+                                        "Content already extracted from current URL. Agent should try a different action."
+                                    );
+                                } else if result.to_uppercase().contains("ERROR") {
+                                    warn!(
+                                        "Executor reported an error: {}. Continuing cautiously.",
+                                        result
+                                    );
+                                    if !result.contains("ERROR_PARSING_JSON")
+                                        && !result.starts_with("ERROR_INVALID_ACTION")
+                                        && let Ok(call) = &next_action
+                                    {
+                                        self.remember(
+                                            MemoryKind::Failure,
+                                            &format!("{} failed: {}", call.name, result),
+                                            &current_url,
+                                        );
+                                    }
+                                } else {
+                                    completed_tasks.push(current_goal.clone());
                                 }
-                            } else if result
-                                .trim_matches('"')
-                                .eq_ignore_ascii_case("CONTENT_ALREADY_EXTRACTED")
-                            {
-                                println!(
-                                    "Content already extracted from current URL. Agent should try a different action."
-                                );
-                                self.try_add_to_external_history(format!(
-                                    "Step {}: Content already extracted from current URL",
-                                    current_step
-                                ))
-                                .await;
-                            } else if result.to_uppercase().contains("ERROR") {
-                                println!(
-                                    "Executor reported an error: {}. Continuing cautiously.",
-                                    result
-                                );
-                                if result.contains("ERROR_PARSING_JSON") {
-                                    self.try_add_to_external_history(format!(
-                                        "Step {}: Agent response was not valid JSON. Previous response: {}",
-                                        current_step, ai_response
-                                    )).await;
+                            }
+                            Err(e) => {
+                                error!("Critical error executing task: {}", e);
+                                if let Ok(call) = &next_action {
+                                    self.remember(
+                                        MemoryKind::Failure,
+                                        &format!("{} failed: {}", call.name, e),
+                                        &current_url,
+                                    );
                                 }
-                            } else {
-                                completed_tasks.push(current_goal.clone());
+                                self.history.record(HistoryEntry {
+                                    agent_id: self.id.clone(),
+                                    step: current_step,
+                                    goal: current_goal.clone(),
+                                    action: next_action.as_ref().ok().map(|call| call.name.clone()),
+                                    params: next_action.as_ref().ok().map(|call| call.params.clone()),
+                                    result: format!("ERROR: {}", e),
+                                    note: Some("Critical error during execution".to_string()),
+                                    url: Some(current_url.clone()),
+                                    started_at,
+                                    finished_at: now_millis(),
+                                });
+                                failure = Some(format!("Critical error during execution: {}", e));
+                                step_trace.error = failure.clone();
+                                self.finish_step_trace(step_trace).await;
+                                return true;
                             }
                         }
-                        Err(e) => {
-                            eprintln!("Critical error executing task: {}", e);
-                            self.try_add_to_external_history(format!(
-                                "Step {}: Critical error during execution: {}",
-                                current_step, e
-                            ))
-                            .await;
-                            final_answer = format!("Critical error during execution: {}", e);
-                            break;
-                        }
+                    }
+                    Err(e) => {
+                        error!("Error generating task agent AI response: {}", e);
+                        self.history.record(HistoryEntry {
+                            agent_id: self.id.clone(),
+                            step: current_step,
+                            goal: current_goal.clone(),
+                            action: None,
+                            params: None,
+                            result: format!("ERROR: {}", e),
+                            note: Some("Error from Task Agent".to_string()),
+                            url: Some(current_url.clone()),
+                            started_at,
+                            finished_at: now_millis(),
+                        });
+                        failure = Some(format!("Error in task agent: {}", e));
+                        step_trace.error = failure.clone();
+                        self.finish_step_trace(step_trace).await;
+                        return true;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error generating task agent AI response: {}", e);
-                    self.try_add_to_external_history(format!(
-                        "Step {}: Error from Task Agent: {}",
-                        current_step, e
-                    ))
-                    .await;
-                    final_answer = format!("Error in task agent: {}", e);
-                    break;
-                }
-            }
 
-            // Highlight all interactive elements and take a screenshot
-            let screenshot_path = format!("images/interactive_elements_step_{}.png", current_step);
-            if !interactive_elements.is_empty() {
-                match self
-                    .highlight_and_screenshot_interactive_elements(
-                        &interactive_elements,
-                        &screenshot_path,
-                    )
-                    .await
-                {
-                    Ok(b64) => self.last_screenshot = Some(b64),
-                    Err(e) => eprintln!("Failed to highlight elements or take screenshot: {}", e),
-                }
-            }
+                self.finish_step_trace(step_trace).await;
 
-            // Small delay between steps
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                // Small delay between steps
+                tokio::time::sleep(self.step_delay).await;
+                false
+            }
+            .instrument(step_span)
+            .await;
+            if stop {
+                break;
+            }
         }
 
-        if current_step >= max_steps {
-            println!("Reached maximum steps ({}). Exiting loop.", max_steps);
-            let last = self.get_last_history_or("N/A").await;
-            final_answer = format!("Reached max steps. Last known state: {}", last);
+        if !finished && failure.is_none() && current_step >= max_steps {
+            warn!("Reached maximum steps ({}). Exiting loop.", max_steps);
+            let last = self.get_last_history_or("N/A");
+            failure = Some(format!(
+                "Reached max steps ({}) without completing the goal. Last known state: {}",
+                max_steps, last
+            ));
         }
 
-        println!("\n--- Agent {} Finished ---", self.id);
-        if !final_answer.is_empty() {
-            println!("Agent Final Answer: {}", final_answer);
+        if let Some(reason) = failure {
+            warn!("Agent {} did not complete its goal: {}", self.id, reason);
+            Err(reason.into())
+        } else if !final_answer.is_empty() {
+            info!("Agent Final Answer: {}", final_answer);
             Ok(final_answer)
         } else {
             let summary = format!(
@@ -339,64 +597,128 @@ impl AIAgent {
                 self.id,
                 completed_tasks.len()
             );
-            println!("{}", summary);
+            info!("{}", summary);
             Ok(summary)
         }
     }
 
+    #[instrument(name = "action", skip_all, fields(name = %action.name))]
     async fn execute_action(
         &mut self,
-        string_response: String,
-        action: String,
+        action: &ActionCall,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        println!("Executing action: {}", action);
+        info!("Executing action: {}", action.to_value());
 
         // Get current URL before executing
-        let current_url = match self.driver.current_url().await {
-            Ok(url) => url.to_string(),
+        let current_url = match self.browser.current_url().await {
+            Ok(url) => url,
             Err(e) => {
-                eprintln!("Failed to get current URL: {}", e);
+                warn!("Failed to get current URL: {}", e);
                 String::from("Unknown")
             }
         };
 
         // Check if the action is extract_content and URL already extracted
-        if let Ok(action_json) = serde_json::from_str::<serde_json::Value>(&action) {
-            if action_json.get("extract_content").is_some() {
-                if self.has_extracted_content_from_url(&current_url) {
-                    println!(
-                        "Content already extracted from URL: {}. Skipping extraction.",
-                        current_url
-                    );
-                    return Ok("CONTENT_ALREADY_EXTRACTED".to_string());
-                }
-            }
+        if action.name == "extract_content" && self.has_extracted_content_from_url(&current_url) {
+            info!(
+                "Content already extracted from URL: {}. Skipping extraction.",
+                current_url
+            );
+            return Ok("CONTENT_ALREADY_EXTRACTED".to_string());
         }
 
-        let result = execute_task(string_response, &self.driver, action.clone()).await?;
+        let ctx = ActionContext {
+            browser: self.browser.as_ref(),
+            agent_id: &self.id,
+            elements: &self.elements,
+            output_dir: &self.output_dir,
+            extracted: Default::default(),
+        };
+        let result = self
+            .actions
+            .dispatch(&ctx, action)
+            .await
+            .map_err(|e| e as Box<dyn std::error::Error>)?;
+        self.last_extracted = ctx.take_extracted();
 
         // If extract_content was successful, mark URL as extracted
-        if let Ok(action_json) = serde_json::from_str::<serde_json::Value>(&action) {
-            if action_json.get("extract_content").is_some() && result == "CONTINUE" {
-                self.mark_url_as_extracted(current_url.clone());
-                println!("Marked URL as extracted: {}", current_url);
-            }
+        if action.name == "extract_content" && result == "CONTINUE" {
+            self.mark_url_as_extracted(current_url.clone());
+            debug!("Marked URL as extracted: {}", current_url);
+            self.remember(
+                MemoryKind::VisitedPage,
+                &format!("Extracted content while working on: {}", self.goal),
+                &current_url,
+            );
         }
 
-        println!("Action executed successfully.");
+        debug!("Action executed successfully.");
         Ok(result)
     }
 
+    /// The memories most relevant to the current goal and page, at most
+    /// [`MEMORIES_IN_PROMPT`].
+    async fn relevant_memories(&self, current_goal: &str, current_url: &str) -> Vec<MemoryRecord> {
+        match &self.memory {
+            Some(memory) => {
+                memory
+                    .retrieve(&MemoryQuery {
+                        url: Some(current_url.to_string()),
+                        text: format!("{}\n{}", self.goal, current_goal),
+                        limit: MEMORIES_IN_PROMPT,
+                    })
+                    .await
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// The page section of the prompt, built from [`AIAgent::elements`]. Falls back to the
+    /// element list if the accessibility tree can't be collected.
+    async fn describe_page(&self) -> String {
+        if self.observation.mode == ObservationMode::AccessibilityTree {
+            match accessibility_tree(self.browser.as_ref(), &self.elements).await {
+                Ok(tree) if !tree.is_empty() => {
+                    return format!(
+                        "Here is the accessibility tree of the page. Interactive elements carry their number in brackets:\n{}",
+                        render_tree(&tree, self.observation.token_budget)
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Failed to get the accessibility tree: {}. Using the element list.",
+                    e
+                ),
+            }
+        }
+        let elements = if self.elements.is_empty() {
+            String::from("No interactive elements found.")
+        } else {
+            self.elements
+                .iter()
+                .map(|element| element.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        format!(
+            "Here are the interactive elements on the page:\n{}",
+            elements
+        )
+    }
+
     fn gen_prompt(
         &self,
         high_level_plan: String,
         current_url: String,
         task_history: String,
-        interactive_elements: String,
+        page: String,
+        memories: &[MemoryRecord],
     ) -> String {
-        // include screenshot data if available
-        let screenshot_info = if let Some(ref b64) = self.last_screenshot {
-            format!("\nScreenshot (base64):\n{}", b64)
+        // the screenshot itself is attached as an image
+        let screenshot_info = if self.last_screenshot.is_some() {
+            String::from(
+                "\nA screenshot of the page is attached. Interactive elements are outlined and numbered on it.",
+            )
         } else {
             String::new()
         };
@@ -413,6 +735,15 @@ impl AIAgent {
             )
         };
 
+        let memory_info = if memories.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "Here is what you remember from earlier runs:\n{}\n\n",
+                MemoryManager::render(memories)
+            )
+        };
+
         let current_url_extracted_status = if self.has_extracted_content_from_url(&current_url) {
             "\n\nIMPORTANT: Content has already been extracted from the current URL. Consider taking a different action instead of extracting content again."
         } else {
@@ -421,22 +752,24 @@ impl AIAgent {
 
         // Construct prompt with aligned placeholders and arguments
         format!(
-            "Agent Role: {}\nAgent Backstory: {}\nAgent Goal: {}\nAgent Tools: {}\nContext: {}\n\n\
+            "Agent Role: {}\nAgent Backstory: {}\nAgent Goal: {}\nAgent Description: {}\nAgent Tools: {}\nContext: {}\n\n\
 Here is the current task to accomplish:\n{}\n\n\
 Here is the current URL:\n{}\n\n\
 Here is the task history:\n{}\n\n\
-Here are the interactive elements on the page:\n{}\n\n\
-{}\n{}\n{}\n\n\
+{}\n\n\
+{}{}\n{}\n{}\n\n\
 Based on your role, goal, and the current task, determine the next action to take. Use the available tools and context to make the best decision. Output your decision in the JSON format specified.",
             self.role,
             self.backstory,
             self.goal,
+            self.description,
             self.tools,
             self.context,
             high_level_plan,
             current_url,
             task_history,
-            interactive_elements,
+            page,
+            memory_info,
             extracted_urls_info,
             current_url_extracted_status,
             screenshot_info
@@ -444,30 +777,12 @@ Based on your role, goal, and the current task, determine the next action to tak
     }
 
     /// Highlights all interactive elements with a colored border and overlays a styled label, then takes a screenshot.
+    /// Labels show the element ids of [`AIAgent::elements`]. The highlights are removed again
+    /// once the PNG is saved to `screenshot_path`; it is returned.
     async fn highlight_and_screenshot_interactive_elements(
         &self,
-        interactive_elements: &std::collections::HashMap<String, thirtyfour::By>,
         screenshot_path: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        // Collect all elements first to avoid multiple await calls
-        let mut elements_to_highlight = Vec::new();
-
-        for (index, locator) in interactive_elements.values().enumerate() {
-            if let Ok(elements) = self.driver.find_all(locator.clone()).await {
-                for element in elements {
-                    elements_to_highlight.push((element, index));
-                    // Only take the first 200 elements
-                    if elements_to_highlight.len() >= 200 {
-                        break;
-                    }
-                }
-            }
-            // Break outer loop if we have 200 elements
-            if elements_to_highlight.len() >= 200 {
-                break;
-            }
-        }
-
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
         // Remove previous highlights if any
         let cleanup_script = r#"
             const containerId = 'playwright-highlight-container';
@@ -476,10 +791,11 @@ Based on your role, goal, and the current task, determine the next action to tak
             // Remove all overlays and labels
             document.querySelectorAll('.playwright-highlight-label').forEach(e => e.remove());
         "#;
-        let _ = self.driver.execute(cleanup_script, vec![]).await;
+        let _ = self.browser.execute_script(cleanup_script, vec![]).await;
 
-        // Create a single script to highlight all elements at once
-        if !elements_to_highlight.is_empty() {
+        // Create a single script to highlight all elements at once; the locators are
+        // resolved in the page so this works the same on every backend.
+        if !self.elements.is_empty() {
             let script = r#"
                 const colors = [
                     '#FF0000', '#00FF00', '#0000FF', '#FFA500', '#800080', '#008080',
@@ -500,9 +816,14 @@ Based on your role, goal, and the current task, determine the next action to tak
                     container.style.backgroundColor = 'transparent';
                     document.body.appendChild(container);
                 }
-                for (let i = 0; i < arguments.length; i += 2) {
-                    const element = arguments[i];
-                    const index = arguments[i + 1];
+                const elementsToHighlight = [];
+                for (const [locator, index] of arguments[0]) {
+                    for (const element of resolveLocator(locator)) {
+                        elementsToHighlight.push([element, index]);
+                    }
+                }
+                // Only highlight the first 200 elements
+                for (const [element, index] of elementsToHighlight.slice(0, 200)) {
                     const color = colors[index % colors.length];
                     const backgroundColor = color + '1A'; // 10% opacity
                     const rects = element.getClientRects();
@@ -549,29 +870,76 @@ Based on your role, goal, and the current task, determine the next action to tak
                 }
             "#;
 
-            // Prepare arguments array
-            let mut args = Vec::new();
-            for (element, index) in &elements_to_highlight {
-                args.push(element.to_json()?);
-                args.push(serde_json::json!(index));
-            }
+            let locators: Vec<Value> = self
+                .elements
+                .iter()
+                .map(|element| serde_json::json!([element.locator.to_json(), element.id]))
+                .collect();
 
             // Execute single script with all elements
-            let _ = self.driver.execute(script, args).await;
+            let script = format!("{}{}", RESOLVE_LOCATOR_JS, script);
+            let _ = self
+                .browser
+                .execute_script(&script, vec![Value::Array(locators)])
+                .await;
         }
 
         // Ensure the images directory exists
-        if let Some(parent) = std::path::Path::new(screenshot_path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)?;
-            }
+        if let Some(parent) = std::path::Path::new(screenshot_path).parent()
+            && !parent.exists()
+        {
+            std::fs::create_dir_all(parent)?;
         }
 
         // Take screenshot
-        let png_data = self.driver.screenshot_as_png().await?;
+        let png_data = self
+            .browser
+            .screenshot_png()
+            .await
+            .map_err(|e| e as Box<dyn std::error::Error>)?;
+        // Take the labels down again so they don't end up in extracted page text
+        let _ = self.browser.execute_script(cleanup_script, vec![]).await;
         std::fs::write(screenshot_path, &png_data)?;
-        // convert to base64 for AI prompt
-        let b64 = base64::encode(&png_data);
-        Ok(b64)
+        Ok(png_data)
+    }
+}
+
+/// Longest extracted content kept in a step trace.
+const MAX_EXTRACTED_CHARS: usize = 20_000;
+
+fn truncate_extracted(content: String) -> String {
+    match content.char_indices().nth(MAX_EXTRACTED_CHARS) {
+        Some((end, _)) => format!("{}… [truncated]", &content[..end]),
+        None => content,
+    }
+}
+
+/// `id` with anything but ASCII letters, digits, `-` and `_` replaced, for file names.
+fn file_safe(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Facts the task agent asked to keep for later runs, from the optional `remember` array of
+/// its response.
+fn remember_notes(response: &str) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<Value>(response) else {
+        return Vec::new();
+    };
+    match &json["remember"] {
+        Value::String(note) => vec![note.clone()],
+        Value::Array(notes) => notes
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
     }
 }
@@ -0,0 +1,219 @@
+use crate::agent::action::{Action, ActionError, DOCUMENT_FORMATS};
+use crate::agent::executor::execute_task;
+use crate::agent::registry::{ActionContext, ActionHandler, ActionResult};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+/// Registry adapter for the actions of the typed [`Action`] enum. Validation and
+/// execution go through [`Action::from_value`] and [`execute_task`], so the built-ins keep
+/// their stricter checks (e.g. URL scheme) on top of the schema.
+struct BuiltinAction {
+    name: &'static str,
+    description: &'static str,
+    parameters: Value,
+}
+
+#[async_trait]
+impl ActionHandler for BuiltinAction {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
+
+    fn validate(&self, params: &Value) -> Result<(), ActionError> {
+        self.to_action(params).map(|_| ())
+    }
+
+    async fn execute(&self, ctx: &ActionContext<'_>, params: &Value) -> ActionResult {
+        let action = self.to_action(params)?;
+        Ok(execute_task(ctx, &action).await?)
+    }
+}
+
+impl BuiltinAction {
+    fn to_action(&self, params: &Value) -> Result<Action, ActionError> {
+        Action::from_value(&json!({ self.name: params })).map_err(|e| match e {
+            ActionError::UnknownAction(reason) => ActionError::InvalidParameters {
+                action: self.name.to_string(),
+                reason,
+            },
+            other => other,
+        })
+    }
+}
+
+fn builtin(
+    name: &'static str,
+    description: &'static str,
+    parameters: Value,
+) -> Arc<dyn ActionHandler> {
+    Arc::new(BuiltinAction {
+        name,
+        description,
+        parameters,
+    })
+}
+
+fn no_parameters() -> Value {
+    json!({ "type": "object", "properties": {}, "additionalProperties": false })
+}
+
+/// An object naming its element by `element` id or, failing that, by CSS `selector`, plus
+/// the given extra properties.
+fn element_target(extra: Value) -> Value {
+    let alternative = |key: &str, schema: Value| {
+        let mut properties = extra.as_object().cloned().unwrap_or_default();
+        properties.insert(key.to_string(), schema);
+        let mut required: Vec<String> = properties.keys().cloned().collect();
+        required.sort();
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false
+        })
+    };
+    json!({
+        "anyOf": [
+            alternative("element", json!({ "type": "integer", "minimum": 1 })),
+            alternative("selector", json!({ "type": "string", "minLength": 1 })),
+        ]
+    })
+}
+
+/// Handlers for every built-in action.
+pub fn builtin_handlers() -> Vec<Arc<dyn ActionHandler>> {
+    vec![
+        builtin(
+            "go_to_url",
+            "Open the specified URL in a new browser tab.",
+            json!({
+                "type": "object",
+                "properties": { "url": { "type": "string", "minLength": 1 } },
+                "required": ["url"],
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "extract_content",
+            "Extract and return the text content from the current page's <body>.",
+            no_parameters(),
+        ),
+        builtin(
+            "click_element",
+            "Click an element, given by its number in the element list or by CSS selector.",
+            element_target(json!({})),
+        ),
+        builtin(
+            "fill_form",
+            "Fill form fields (by element number or CSS selector) with provided values.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "form_data": {
+                        "type": "array",
+                        "minItems": 1,
+                        "items": element_target(json!({ "value": { "type": "string" } }))
+                    }
+                },
+                "required": ["form_data"],
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "fill_form_with_user_input_credentials",
+            "Fill form fields (by element number or CSS selector) by prompting the user for each value. Use for credentials.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "form_data": {
+                        "type": "array",
+                        "minItems": 1,
+                        "items": {
+                            "anyOf": [
+                                { "type": "integer", "minimum": 1 },
+                                { "type": "string", "minLength": 1 }
+                            ]
+                        }
+                    }
+                },
+                "required": ["form_data"],
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "search_query",
+            "Search for the text using DuckDuckGo.",
+            json!({
+                "type": "object",
+                "properties": { "query": { "type": "string", "minLength": 1 } },
+                "required": ["query"],
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "go_back",
+            "Go back to the previous page, e.g. once content has been extracted and nothing else is needed from the page.",
+            no_parameters(),
+        ),
+        builtin(
+            "create_document",
+            "Create and save a document with the given content and format.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "filename": { "type": "string", "minLength": 1 },
+                    "content": { "type": "string" },
+                    "format": { "type": "string", "enum": DOCUMENT_FORMATS }
+                },
+                "required": ["filename", "content", "format"],
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "generate_document",
+            "Generate document content using AI based on a task description and save it.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "task_description": { "type": "string", "minLength": 1 },
+                    "filename": { "type": "string", "minLength": 1 },
+                    "format": { "type": "string", "enum": DOCUMENT_FORMATS }
+                },
+                "required": ["task_description", "filename", "format"],
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "final_answer",
+            "Report the answer to the current task once it has been found.",
+            json!({
+                "type": "object",
+                "properties": { "answer": { "type": "string" } },
+                "additionalProperties": false
+            }),
+        ),
+        builtin(
+            "done",
+            "Signal that the agent has completed its task and should move on to the next agent.",
+            no_parameters(),
+        ),
+        builtin(
+            "stop",
+            "Stop the whole task and report the final answer.",
+            json!({
+                "type": "object",
+                "properties": { "final_answer": { "type": "string" } },
+                "additionalProperties": false
+            }),
+        ),
+    ]
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One step of an agent: the action it chose and what came of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub agent_id: String,
+    pub step: usize,
+    /// The part of the agent's goal it was working on.
+    pub goal: String,
+    /// Action name, if the model produced a valid action.
+    pub action: Option<String>,
+    pub params: Option<Value>,
+    /// Control signal or error returned by the action, e.g. `CONTINUE`.
+    pub result: String,
+    /// Feedback for the model, e.g. why its action was rejected.
+    pub note: Option<String>,
+    /// Page the agent was on when it chose the action.
+    pub url: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+impl HistoryEntry {
+    /// Single-line form used in the task prompt.
+    pub fn render(&self) -> String {
+        let mut line = format!("Step {}", self.step);
+        if let Some(action) = &self.action {
+            match &self.params {
+                Some(params) if params.as_object().is_some_and(|p| !p.is_empty()) => {
+                    line.push_str(&format!(": {} {}", action, params))
+                }
+                _ => line.push_str(&format!(": {}", action)),
+            }
+        }
+        if let Some(url) = &self.url {
+            line.push_str(&format!(" on {}", url));
+        }
+        line.push_str(&format!(" -> {}", self.result));
+        if let Some(note) = &self.note {
+            line.push_str(&format!(" ({})", note));
+        }
+        line
+    }
+}
+
+/// Milliseconds since the Unix epoch, for [`HistoryEntry`] timestamps.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Which other agents' history an agent sees in its prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HistorySharing {
+    /// Only its own steps.
+    #[default]
+    Isolated,
+    /// Its own steps plus those of the steps it needs context from.
+    Dependencies,
+    /// Every step of the run so far.
+    Run,
+}
+
+impl std::str::FromStr for HistorySharing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "isolated" | "own" => Ok(HistorySharing::Isolated),
+            "dependencies" | "deps" => Ok(HistorySharing::Dependencies),
+            "run" | "all" => Ok(HistorySharing::Run),
+            other => Err(format!("Unknown history sharing mode: {}", other)),
+        }
+    }
+}
+
+/// Every history entry of one run, in the order they were recorded. Cloning shares the
+/// same entries.
+#[derive(Debug, Clone, Default)]
+pub struct RunHistory {
+    entries: Arc<Mutex<Vec<HistoryEntry>>>,
+}
+
+impl RunHistory {
+    pub fn new() -> Self {
+        RunHistory::default()
+    }
+
+    pub fn push(&self, entry: HistoryEntry) {
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
+/// An agent's handle on the run history: it records under its own id and reads back what
+/// its [`HistorySharing`] mode allows.
+#[derive(Debug, Clone)]
+pub struct AgentHistory {
+    agent_id: String,
+    run: RunHistory,
+    /// Other agents whose entries are visible, or `None` for the whole run.
+    shared_with: Option<HashSet<String>>,
+}
+
+impl AgentHistory {
+    /// A history of its own, not shared with any other agent.
+    pub fn new(agent_id: impl Into<String>) -> Self {
+        AgentHistory {
+            agent_id: agent_id.into(),
+            run: RunHistory::new(),
+            shared_with: Some(HashSet::new()),
+        }
+    }
+
+    /// A view of `run` for `agent_id`. `dependencies` are the ids of the agents it takes
+    /// context from, used by [`HistorySharing::Dependencies`].
+    pub fn in_run(
+        agent_id: impl Into<String>,
+        run: RunHistory,
+        sharing: HistorySharing,
+        dependencies: &[String],
+    ) -> Self {
+        let shared_with = match sharing {
+            HistorySharing::Isolated => Some(HashSet::new()),
+            HistorySharing::Dependencies => Some(dependencies.iter().cloned().collect()),
+            HistorySharing::Run => None,
+        };
+        AgentHistory {
+            agent_id: agent_id.into(),
+            run,
+            shared_with,
+        }
+    }
+
+    pub fn agent_id(&self) -> &str {
+        &self.agent_id
+    }
+
+    /// Records an entry under this agent's id.
+    pub fn record(&self, mut entry: HistoryEntry) {
+        entry.agent_id = self.agent_id.clone();
+        self.run.push(entry);
+    }
+
+    /// This agent's own entries.
+    pub fn own(&self) -> Vec<HistoryEntry> {
+        self.run
+            .entries()
+            .into_iter()
+            .filter(|entry| entry.agent_id == self.agent_id)
+            .collect()
+    }
+
+    pub fn last_own(&self) -> Option<HistoryEntry> {
+        self.own().pop()
+    }
+
+    /// The entries this agent may see, oldest first.
+    pub fn visible(&self) -> Vec<HistoryEntry> {
+        self.run
+            .entries()
+            .into_iter()
+            .filter(|entry| {
+                entry.agent_id == self.agent_id
+                    || self
+                        .shared_with
+                        .as_ref()
+                        .is_none_or(|ids| ids.contains(&entry.agent_id))
+            })
+            .collect()
+    }
+
+    /// The last `limit` visible entries as prompt lines. Other agents' entries are
+    /// prefixed with their id.
+    pub fn render_recent(&self, limit: usize) -> Vec<String> {
+        let visible = self.visible();
+        let skip = visible.len().saturating_sub(limit);
+        visible
+            .into_iter()
+            .skip(skip)
+            .map(|entry| {
+                if entry.agent_id == self.agent_id {
+                    entry.render()
+                } else {
+                    format!("[{}] {}", entry.agent_id, entry.render())
+                }
+            })
+            .collect()
+    }
+}
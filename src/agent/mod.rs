@@ -1,4 +1,11 @@
+pub mod action;
+#[allow(clippy::module_inception)]
 pub mod agent;
+pub mod builtin_actions;
 pub mod executor;
+pub mod history;
+pub mod plan_executor;
+pub mod plan_validator;
 pub mod planner;
+pub mod registry;
 pub mod task;
@@ -0,0 +1,605 @@
+use crate::agent::agent::{AIAgent, DEFAULT_MAX_STEPS};
+use crate::agent::history::{AgentHistory, HistorySharing, RunHistory};
+use crate::agent::planner::{
+    AgentStep, FinishedStep, MAX_PLAN_REPAIR_ATTEMPTS, PlannerAgentPlan, replan_agent,
+};
+use crate::agent::registry::ActionRegistry;
+use crate::browser_control::accessibility::ObservationConfig;
+use crate::browser_control::backend::{BackendKind, BrowserOptions};
+use crate::browser_control::capabilities::BrowserCapabilities;
+use crate::browser_control::session_manager::BrowserSessionManager;
+use crate::llm::image::VisionConfig;
+use crate::memory::memory_manager::MemoryManager;
+use crate::session::recorder::SessionRecorder;
+use crate::trace::{TraceEvent, TraceWriter};
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, instrument, warn};
+
+/// Dependencies between plan steps.
+///
+/// A step depends on every step it `needs_context_from`. Steps with `run_in_parallel: false`
+/// act as barriers: they wait for every earlier step, and every later step waits for
+/// them, which preserves the sequential behaviour for plans that don't opt in.
+#[derive(Debug, Clone)]
+pub struct PlanGraph {
+    /// `deps[i]` lists the steps that must finish before step `i` starts.
+    pub deps: Vec<Vec<usize>>,
+}
+
+impl PlanGraph {
+    pub fn from_plan(plan: &PlannerAgentPlan) -> Result<Self, String> {
+        let count = plan.steps.len();
+        let mut deps: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut last_barrier: Option<usize> = None;
+
+        for (i, step) in plan.steps.iter().enumerate() {
+            for source in plan.context_sources(i)? {
+                if source == i {
+                    return Err(format!(
+                        "Step {} ({}) needs context from itself",
+                        i, step.id
+                    ));
+                }
+                deps[i].push(source);
+            }
+
+            if !step.run_in_parallel {
+                deps[i].extend(0..i);
+                last_barrier = Some(i);
+            } else if let Some(barrier) = last_barrier {
+                deps[i].push(barrier);
+            }
+
+            deps[i].sort_unstable();
+            deps[i].dedup();
+        }
+
+        let graph = PlanGraph { deps };
+        graph.topological_order()?;
+        Ok(graph)
+    }
+
+    /// Orders the steps so every step comes after its dependencies, or reports a cycle.
+    pub fn topological_order(&self) -> Result<Vec<usize>, String> {
+        let count = self.deps.len();
+        let mut remaining: Vec<usize> = self.deps.iter().map(Vec::len).collect();
+        let mut ready: VecDeque<usize> = (0..count).filter(|&i| remaining[i] == 0).collect();
+        let mut order = Vec::with_capacity(count);
+
+        while let Some(step) = ready.pop_front() {
+            order.push(step);
+            for (dependent, deps) in self.deps.iter().enumerate() {
+                if deps.contains(&step) {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        ready.push_back(dependent);
+                    }
+                }
+            }
+        }
+
+        if order.len() != count {
+            let stuck: Vec<String> = (0..count)
+                .filter(|i| !order.contains(i))
+                .map(|i| i.to_string())
+                .collect();
+            return Err(format!(
+                "Plan has a dependency cycle between steps {}",
+                stuck.join(", ")
+            ));
+        }
+        Ok(order)
+    }
+}
+
+/// Result of running one plan step.
+#[derive(Debug, Clone)]
+pub struct StepOutcome {
+    pub index: usize,
+    pub id: String,
+    /// The agent's final answer, or the reason it failed.
+    pub result: Result<String, String>,
+}
+
+impl StepOutcome {
+    pub fn output(&self) -> Option<&str> {
+        self.result.as_deref().ok()
+    }
+}
+
+/// Runs a plan as a DAG, starting every step whose dependencies have finished, up to
+/// `max_concurrency` at a time. Each running step has its own browser session; a step
+/// continues in the session of the step it depends on when that one is free.
+pub struct PlanExecutor {
+    browser: BrowserOptions,
+    reuse_sessions: bool,
+    sessions: Option<Arc<BrowserSessionManager>>,
+    max_concurrency: usize,
+    actions: Arc<ActionRegistry>,
+    recorder: Option<Arc<SessionRecorder>>,
+    replanning: Option<Replanning>,
+    max_steps: usize,
+    output_dir: PathBuf,
+    memory: Option<Arc<MemoryManager>>,
+    history_sharing: HistorySharing,
+    trace: Option<Arc<TraceWriter>>,
+    screenshot_dir: PathBuf,
+    vision: VisionConfig,
+    observation: ObservationConfig,
+}
+
+/// Settings for revising the plan when a step fails or stops early.
+#[derive(Debug, Clone)]
+pub struct Replanning {
+    /// The original user request, given to the planner for context.
+    pub user_task: String,
+    /// How many times the plan may be revised during one run.
+    pub max_replans: usize,
+}
+
+impl PlanExecutor {
+    pub fn new(webdriver_url: impl Into<String>) -> Self {
+        PlanExecutor {
+            browser: BrowserOptions::new(webdriver_url),
+            reuse_sessions: true,
+            sessions: None,
+            max_concurrency: 1,
+            actions: Arc::new(ActionRegistry::with_builtins()),
+            recorder: None,
+            replanning: None,
+            max_steps: DEFAULT_MAX_STEPS,
+            output_dir: PathBuf::from("documents"),
+            memory: None,
+            history_sharing: HistorySharing::default(),
+            trace: None,
+            screenshot_dir: PathBuf::from("images"),
+            vision: VisionConfig::default(),
+            observation: ObservationConfig::default(),
+        }
+    }
+
+    /// Maximum number of steps (and browser sessions) running at once. Clamped to 1.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Step limit for each agent, see [`AIAgent::with_max_steps`].
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn with_output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    /// Starts the browser sessions without a visible window.
+    pub fn with_headless(mut self, headless: bool) -> Self {
+        self.browser.headless = headless;
+        self
+    }
+
+    /// Which [`BrowserBackend`](crate::browser_control::backend::BrowserBackend) drives
+    /// the sessions. Defaults to WebDriver at the URL given to [`PlanExecutor::new`].
+    pub fn with_backend(mut self, backend: BackendKind) -> Self {
+        self.browser.backend = backend;
+        self
+    }
+
+    /// Window size, user agent, proxy, locale and other settings for new sessions.
+    pub fn with_capabilities(mut self, capabilities: BrowserCapabilities) -> Self {
+        self.browser.capabilities = capabilities;
+        self
+    }
+
+    /// DevTools endpoint the Playwright backend attaches to instead of launching Chromium.
+    pub fn with_cdp_url(mut self, cdp_url: Option<String>) -> Self {
+        self.browser.cdp_url = cdp_url;
+        self
+    }
+
+    /// Whether a finished step's browser session is kept for later steps, see
+    /// [`BrowserSessionManager::with_reuse`].
+    pub fn with_session_reuse(mut self, reuse: bool) -> Self {
+        self.reuse_sessions = reuse;
+        self
+    }
+
+    /// Takes browser sessions from `sessions` instead of a manager of its own. The caller
+    /// is then responsible for calling [`BrowserSessionManager::shutdown`], which lets it
+    /// clean up when a run is interrupted. The browser settings of this executor are
+    /// ignored.
+    pub fn with_sessions(mut self, sessions: Arc<BrowserSessionManager>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Where agents save their annotated screenshots, see [`AIAgent::with_screenshot_dir`].
+    pub fn with_screenshot_dir(mut self, screenshot_dir: impl Into<PathBuf>) -> Self {
+        self.screenshot_dir = screenshot_dir.into();
+        self
+    }
+
+    /// How screenshots are sent to the model, see [`AIAgent::with_vision`].
+    pub fn with_vision(mut self, vision: VisionConfig) -> Self {
+        self.vision = vision;
+        self
+    }
+
+    /// How agents see the page, see [`AIAgent::with_observation`].
+    pub fn with_observation(mut self, observation: ObservationConfig) -> Self {
+        self.observation = observation;
+        self
+    }
+
+    pub fn with_actions(mut self, actions: Arc<ActionRegistry>) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    /// Which other steps' history each agent sees. Every call to
+    /// [`PlanExecutor::execute`] starts a fresh history.
+    pub fn with_history_sharing(mut self, sharing: HistorySharing) -> Self {
+        self.history_sharing = sharing;
+        self
+    }
+
+    /// Long-term memory shared by every step's agent.
+    pub fn with_memory(mut self, memory: Arc<MemoryManager>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    pub fn with_recorder(mut self, recorder: Arc<SessionRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Writes plan revisions, step timings and every agent step to `trace`.
+    pub fn with_trace(mut self, trace: Arc<TraceWriter>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// When a step fails, stop starting new steps, let the running ones finish and ask
+    /// the planner to revise the rest of the plan. Without this, downstream steps run
+    /// with the failure noted in their context.
+    pub fn with_replanning(mut self, replanning: Replanning) -> Self {
+        self.replanning = Some(replanning);
+        self
+    }
+
+    /// Executes every step of `plan` and returns their outcomes in plan order. If the plan
+    /// was revised along the way, the outcomes follow the revised plan.
+    ///
+    /// Browser sessions are closed before returning, whether or not the run succeeded,
+    /// unless they come from a manager given to [`PlanExecutor::with_sessions`].
+    ///
+    /// Runs in a `run` span; each step gets a `plan_step` span inside it.
+    #[instrument(
+        name = "run",
+        skip_all,
+        fields(
+            steps = plan.steps.len(),
+            run_id = self.trace.as_ref().map(|trace| trace.run_id())
+        )
+    )]
+    pub async fn execute(
+        &self,
+        plan: &PlannerAgentPlan,
+    ) -> Result<Vec<StepOutcome>, Box<dyn std::error::Error>> {
+        let Some(sessions) = &self.sessions else {
+            let sessions = Arc::new(
+                BrowserSessionManager::new(self.browser.clone())
+                    .with_reuse(self.reuse_sessions)
+                    .with_max_idle(self.max_concurrency),
+            );
+            let result = self.execute_with(plan, &sessions).await;
+            sessions.shutdown().await;
+            return result;
+        };
+        self.execute_with(plan, sessions).await
+    }
+
+    async fn execute_with(
+        &self,
+        plan: &PlannerAgentPlan,
+        sessions: &BrowserSessionManager,
+    ) -> Result<Vec<StepOutcome>, Box<dyn std::error::Error>> {
+        let mut plan = plan.clone();
+        let mut outcomes: Vec<Option<StepOutcome>> = vec![None; plan.steps.len()];
+        let history = RunHistory::new();
+        let mut replans = 0;
+        let run_started = Instant::now();
+        self.trace_plan(0, &plan);
+
+        loop {
+            let stop_on_failure = self
+                .replanning
+                .as_ref()
+                .is_some_and(|replanning| replans < replanning.max_replans);
+            if !self
+                .run_steps(&plan, &mut outcomes, &history, sessions, stop_on_failure)
+                .await?
+            {
+                break;
+            }
+
+            let Some(replanning) = &self.replanning else {
+                break;
+            };
+            replans += 1;
+            match self.replan(replanning, &plan, &outcomes).await {
+                Ok((revised, revised_outcomes)) => {
+                    plan = revised;
+                    outcomes = revised_outcomes;
+                    self.trace_plan(replans, &plan);
+                }
+                Err(e) => {
+                    warn!(
+                        "Re-planning failed: {}. Continuing with the current plan.",
+                        e
+                    );
+                    replans = replanning.max_replans;
+                }
+            }
+        }
+
+        let outcomes: Vec<StepOutcome> = outcomes.into_iter().flatten().collect();
+        if let Some(trace) = &self.trace {
+            trace.emit(TraceEvent::RunFinished {
+                duration_ms: run_started.elapsed().as_millis() as u64,
+                steps: outcomes.len(),
+                failed: outcomes.iter().filter(|o| o.result.is_err()).count(),
+            });
+        }
+        Ok(outcomes)
+    }
+
+    fn trace_plan(&self, revision: usize, plan: &PlannerAgentPlan) {
+        if let Some(trace) = &self.trace {
+            match serde_json::to_value(plan) {
+                Ok(plan) => trace.emit(TraceEvent::Plan { revision, plan }),
+                Err(e) => warn!("Failed to serialize plan for the trace: {}", e),
+            }
+        }
+    }
+
+    /// Asks the planner for a revised plan and carries the finished outcomes over to it.
+    async fn replan(
+        &self,
+        replanning: &Replanning,
+        plan: &PlannerAgentPlan,
+        outcomes: &[Option<StepOutcome>],
+    ) -> Result<(PlannerAgentPlan, Vec<Option<StepOutcome>>), Box<dyn std::error::Error>> {
+        let normalized = plan.with_id_references();
+        let mut finished = Vec::new();
+        let mut remaining = Vec::new();
+        for (step, outcome) in normalized.steps.iter().zip(outcomes) {
+            match outcome {
+                Some(outcome) => finished.push(FinishedStep {
+                    step,
+                    result: outcome.result.as_deref().map_err(String::as_str),
+                }),
+                None => remaining.push(step.clone()),
+            }
+        }
+
+        let revised = replan_agent(
+            &replanning.user_task,
+            &finished,
+            &remaining,
+            &self.actions.names(),
+            MAX_PLAN_REPAIR_ATTEMPTS,
+        )
+        .await?;
+        PlanGraph::from_plan(&revised)?;
+
+        let mut revised_outcomes: Vec<Option<StepOutcome>> = outcomes
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, outcome)| {
+                Some(StepOutcome {
+                    index,
+                    ..outcome.clone()
+                })
+            })
+            .collect();
+        revised_outcomes.resize(revised.steps.len(), None);
+        info!("Revised plan: {:?}", revised);
+        Ok((revised, revised_outcomes))
+    }
+
+    /// Runs every step that has not run yet. Returns `true` if it stopped early because a
+    /// step failed and `stop_on_failure` was set.
+    async fn run_steps(
+        &self,
+        plan: &PlannerAgentPlan,
+        outcomes: &mut [Option<StepOutcome>],
+        history: &RunHistory,
+        sessions: &BrowserSessionManager,
+        stop_on_failure: bool,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let graph = PlanGraph::from_plan(plan)?;
+        let count = plan.steps.len();
+        let mut started: Vec<bool> = outcomes.iter().map(Option::is_some).collect();
+        let mut running = FuturesUnordered::new();
+        let mut halted = false;
+
+        loop {
+            let ready: Vec<usize> = if halted {
+                Vec::new()
+            } else {
+                (0..count)
+                    .filter(|&i| {
+                        !started[i] && graph.deps[i].iter().all(|&d| outcomes[d].is_some())
+                    })
+                    .collect()
+            };
+            for i in ready {
+                if running.len() >= self.max_concurrency {
+                    break;
+                }
+                started[i] = true;
+                let context = step_context(plan, i, outcomes);
+                let dependencies: Vec<String> = plan
+                    .context_sources(i)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|source| plan.steps[source].id.clone())
+                    .collect();
+                let step_history = AgentHistory::in_run(
+                    plan.steps[i].id.clone(),
+                    history.clone(),
+                    self.history_sharing,
+                    &dependencies,
+                );
+                info!(
+                    "Starting plan step {} ({}) with {} other step(s) running",
+                    i,
+                    plan.steps[i].id,
+                    running.len()
+                );
+                // Prefer the session of the latest step this one waited for.
+                let session_owners: Vec<String> = graph.deps[i]
+                    .iter()
+                    .rev()
+                    .map(|&d| plan.steps[d].id.clone())
+                    .collect();
+                running.push(self.run_step(
+                    i,
+                    &plan.steps[i],
+                    context,
+                    step_history,
+                    sessions,
+                    session_owners,
+                ));
+            }
+
+            match running.next().await {
+                Some(outcome) => {
+                    match &outcome.result {
+                        Ok(output) => info!("Agent step {} output: {}", outcome.index, output),
+                        Err(e) => {
+                            warn!("Agent step {} failed: {}", outcome.index, e);
+                            if stop_on_failure && !halted {
+                                info!(
+                                    "Waiting for {} running step(s) before re-planning",
+                                    running.len()
+                                );
+                                halted = true;
+                            }
+                        }
+                    }
+                    let index = outcome.index;
+                    outcomes[index] = Some(outcome);
+                }
+                None => break,
+            }
+        }
+
+        Ok(halted)
+    }
+
+    #[instrument(name = "plan_step", skip_all, fields(index, id = %step.id))]
+    async fn run_step(
+        &self,
+        index: usize,
+        step: &AgentStep,
+        context: String,
+        history: AgentHistory,
+        sessions: &BrowserSessionManager,
+        session_owners: Vec<String>,
+    ) -> StepOutcome {
+        let started = Instant::now();
+        if let Some(trace) = &self.trace {
+            trace.emit(TraceEvent::PlanStepStarted {
+                index,
+                id: step.id.clone(),
+            });
+        }
+        let result = async {
+            let browser = sessions
+                .acquire(&session_owners)
+                .await
+                .map_err(|e| e.to_string())?;
+            let mut agent = AIAgent::new(
+                step.id.clone(),
+                step.param("goal"),
+                step.param("description"),
+                step.param("tools"),
+                step.param("role"),
+                step.param("backstory"),
+                context,
+                browser.clone(),
+            )
+            .with_actions(self.actions.clone())
+            .with_max_steps(self.max_steps)
+            .with_output_dir(&self.output_dir)
+            .with_screenshot_dir(&self.screenshot_dir)
+            .with_vision(self.vision.clone())
+            .with_observation(self.observation.clone())
+            .with_history(history);
+            if let Some(recorder) = &self.recorder {
+                agent = agent.with_recorder(recorder.clone());
+            }
+            if let Some(memory) = &self.memory {
+                agent = agent.with_memory(memory.clone());
+            }
+            if let Some(trace) = &self.trace {
+                agent = agent.with_trace(trace.clone());
+            }
+            let result = agent.process().await.map_err(|e| e.to_string());
+            drop(agent);
+            sessions.release(&step.id, browser).await;
+            result
+        }
+        .await;
+
+        if let Some(trace) = &self.trace {
+            trace.emit(TraceEvent::PlanStepFinished {
+                index,
+                id: step.id.clone(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                output: result.as_ref().ok().cloned(),
+                error: result.as_ref().err().cloned(),
+            });
+        }
+
+        StepOutcome {
+            index,
+            id: step.id.clone(),
+            result,
+        }
+    }
+}
+
+/// The context passed to a step: the labelled outputs of the steps it depends on, or
+/// the planner-provided `context` parameter if it has none.
+fn step_context(plan: &PlannerAgentPlan, index: usize, outcomes: &[Option<StepOutcome>]) -> String {
+    let sources = plan.context_sources(index).unwrap_or_default();
+    if sources.is_empty() {
+        return plan.steps[index].param("context");
+    }
+
+    sources
+        .iter()
+        .map(|&source| {
+            let id = &plan.steps[source].id;
+            match outcomes[source].as_ref().map(|outcome| &outcome.result) {
+                Some(Ok(output)) => format!("[Output of step {} ({})]\n{}", source, id, output),
+                Some(Err(e)) => format!("[Step {} ({}) failed: {}]", source, id, e),
+                None => format!("[Step {} ({}) has not run]", source, id),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,84 @@
+use crate::agent::plan_executor::PlanGraph;
+use crate::agent::planner::PlannerAgentPlan;
+use std::collections::HashSet;
+
+/// Splits a step's `tools` parameter ("search_query, go_to_url") into tool names.
+pub fn tool_names(tools: &str) -> Vec<&str> {
+    tools
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Checks a plan before execution and returns every problem found, or an empty list if
+/// the plan can be run.
+///
+/// Checks: at least one step, unique non-empty ids, non-empty goals, tools drawn from
+/// `known_tools`, `needs_context_from` references that resolve to another step, and no
+/// dependency cycles.
+pub fn validate_plan(plan: &PlannerAgentPlan, known_tools: &[&str]) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    if plan.steps.is_empty() {
+        issues.push("The plan has no steps.".to_string());
+        return issues;
+    }
+
+    let mut seen_ids = HashSet::new();
+    for (i, step) in plan.steps.iter().enumerate() {
+        if step.id.trim().is_empty() {
+            issues.push(format!("Step {} has an empty id.", i));
+        } else if !seen_ids.insert(step.id.as_str()) {
+            issues.push(format!(
+                "Step {} reuses the id \"{}\"; ids must be unique.",
+                i, step.id
+            ));
+        }
+
+        if !step.parameters.is_object() {
+            issues.push(format!(
+                "Step {} ({}) must have a `parameters` object.",
+                i, step.id
+            ));
+        }
+        if step.param("goal").trim().is_empty() {
+            issues.push(format!("Step {} ({}) has an empty goal.", i, step.id));
+        }
+
+        let tools = step.param("tools");
+        let unknown: Vec<&str> = tool_names(&tools)
+            .into_iter()
+            .filter(|tool| !known_tools.contains(tool))
+            .collect();
+        if !unknown.is_empty() {
+            issues.push(format!(
+                "Step {} ({}) uses unknown tool(s): {}. Available tools: {}.",
+                i,
+                step.id,
+                unknown.join(", "),
+                known_tools.join(", ")
+            ));
+        }
+
+        match plan.context_sources(i) {
+            Ok(sources) if sources.contains(&i) => {
+                issues.push(format!(
+                    "Step {} ({}) needs context from itself.",
+                    i, step.id
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => issues.push(format!("{}.", e)),
+        }
+    }
+
+    // Only look for cycles once every reference resolves, to avoid duplicate reports.
+    if issues.is_empty()
+        && let Err(e) = PlanGraph::from_plan(plan)
+    {
+        issues.push(format!("{}.", e));
+    }
+
+    issues
+}
@@ -1,12 +1,63 @@
-use project_oculus::utils::generate_ai_response;
+use crate::agent::plan_validator::validate_plan;
+use crate::utils::generate_ai_response;
 use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AgentStep {
     pub id: String,                    // e.g., "web", "data", etc.
     pub parameters: serde_json::Value, // All parameters needed to create the agent
     pub run_in_parallel: bool,
-    pub needs_context_from: Option<usize>, // Index of previous agent step to get context from
+    // Steps whose outputs this agent receives as context. Accepts null, a single
+    // reference or a list of references, for compatibility with older plans.
+    #[serde(default, deserialize_with = "deserialize_context_refs")]
+    pub needs_context_from: Vec<ContextRef>,
+}
+
+/// Reference to another plan step, either by position or by `id`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ContextRef {
+    Index(usize),
+    Id(String),
+}
+
+impl std::fmt::Display for ContextRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContextRef::Index(index) => write!(f, "{}", index),
+            ContextRef::Id(id) => write!(f, "\"{}\"", id),
+        }
+    }
+}
+
+fn deserialize_context_refs<'de, D>(deserializer: D) -> Result<Vec<ContextRef>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(ContextRef),
+        Many(Vec<ContextRef>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        None => Vec::new(),
+        Some(OneOrMany::One(reference)) => vec![reference],
+        Some(OneOrMany::Many(references)) => references,
+    })
+}
+
+impl AgentStep {
+    /// String value of `parameters[key]`, or an empty string if absent.
+    pub fn param(&self, key: &str) -> String {
+        self.parameters
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -14,54 +65,195 @@ pub struct PlannerAgentPlan {
     pub steps: Vec<AgentStep>,
 }
 
-pub async fn planner_agent() -> Result<String, Box<dyn std::error::Error>> {
-    let user_task = project_oculus::utils::get_user_input("Enter the task you want to perform: ");
-    println!("User task for planner: {}", user_task);
-
-    let mut string_response: String = String::new();
-    // Generate the prompt for the planner AI.
-    let instructions = generate_ai_response(
-        &gen_prompt(&user_task),
-        "You are a planning agent that creates detailed execution plans for accomplishing user tasks.",
-    ).await;
-    println!(
-        "Instructions for planner AI: {}",
-        instructions
-            .as_deref()
-            .unwrap_or("No instructions provided")
-    );
+impl PlannerAgentPlan {
+    /// Resolves a context reference to a step index. Ids take precedence; a string that
+    /// matches no id but parses as a number is treated as an index.
+    pub fn resolve(&self, reference: &ContextRef) -> Option<usize> {
+        let index = match reference {
+            ContextRef::Index(index) => *index,
+            ContextRef::Id(id) => match self.steps.iter().position(|step| &step.id == id) {
+                Some(index) => return Some(index),
+                None => id.trim().parse().ok()?,
+            },
+        };
+        (index < self.steps.len()).then_some(index)
+    }
 
-    match instructions {
-        Ok(response) => {
-            string_response = response;
+    /// A copy of the plan where every resolvable context reference points to a step id,
+    /// so steps can be moved or merged into another plan without changing meaning.
+    pub fn with_id_references(&self) -> PlannerAgentPlan {
+        let mut plan = self.clone();
+        for step in &mut plan.steps {
+            for reference in &mut step.needs_context_from {
+                if let Some(index) = self.resolve(reference) {
+                    *reference = ContextRef::Id(self.steps[index].id.clone());
+                }
+            }
         }
-        Err(e) => {
-            eprintln!("Error generating planner AI response: {}", e);
+        plan
+    }
+
+    /// Indices of the steps `step_index` takes context from, in the order listed.
+    pub fn context_sources(&self, step_index: usize) -> Result<Vec<usize>, String> {
+        let step = &self.steps[step_index];
+        let mut sources = Vec::new();
+        for reference in &step.needs_context_from {
+            let source = self.resolve(reference).ok_or_else(|| {
+                format!(
+                    "Step {} ({}) needs context from {}, which is not a step in the plan",
+                    step_index, step.id, reference
+                )
+            })?;
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
         }
+        Ok(sources)
     }
+}
 
-    string_response = string_response
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim()
-        .to_string();
+const PLANNER_SYSTEM_INSTRUCTIONS: &str =
+    "You are a planning agent that creates detailed execution plans for accomplishing user tasks.";
+
+/// How many times the planner is asked to fix an invalid plan before giving up.
+pub const MAX_PLAN_REPAIR_ATTEMPTS: usize = 2;
+
+/// Asks the planner LLM for a plan and validates it, sending the concrete problems back
+/// for up to `max_repair_attempts` repairs. `known_tools` are the action names agents
+/// may list in their `tools` parameter.
+#[instrument(name = "planner", skip_all)]
+pub async fn planner_agent(
+    user_task: &str,
+    known_tools: &[&str],
+    max_repair_attempts: usize,
+) -> Result<PlannerAgentPlan, Box<dyn std::error::Error>> {
+    info!("User task for planner: {}", user_task);
+
+    request_valid_plan(
+        gen_prompt(user_task, known_tools),
+        max_repair_attempts,
+        |plan| validate_plan(plan, known_tools),
+    )
+    .await
+}
+
+/// A plan step that has already run, with its output or failure reason.
+pub struct FinishedStep<'a> {
+    pub step: &'a AgentStep,
+    pub result: Result<&'a str, &'a str>,
+}
+
+/// Asks the planner to revise the not-yet-started part of a plan after a step failed
+/// or stopped early.
+///
+/// Returns the merged plan: the finished steps followed by the revised remaining steps. The merged plan is validated as a
+/// whole, with the same repair loop as [`planner_agent`].
+#[instrument(name = "replanner", skip_all, fields(finished = finished.len(), remaining = remaining.len()))]
+pub async fn replan_agent(
+    user_task: &str,
+    finished: &[FinishedStep<'_>],
+    remaining: &[AgentStep],
+    known_tools: &[&str],
+    max_repair_attempts: usize,
+) -> Result<PlannerAgentPlan, Box<dyn std::error::Error>> {
+    info!(
+        "Re-planning after {} finished step(s), {} step(s) remaining",
+        finished.len(),
+        remaining.len()
+    );
+
+    let prompt = gen_replan_prompt(user_task, finished, remaining, known_tools);
+    let finished_steps: Vec<AgentStep> = finished.iter().map(|f| f.step.clone()).collect();
+    let revised = request_valid_plan(prompt, max_repair_attempts, |revised| {
+        validate_plan(&merge_revised_plan(&finished_steps, revised), known_tools)
+    })
+    .await?;
+
+    Ok(merge_revised_plan(&finished_steps, &revised))
+}
+
+/// Appends `revised` to `finished`. The finished steps are expected to reference their
+/// context by id (see [`PlannerAgentPlan::with_id_references`]) since their indices change.
+pub fn merge_revised_plan(finished: &[AgentStep], revised: &PlannerAgentPlan) -> PlannerAgentPlan {
+    let mut steps: Vec<AgentStep> = finished.to_vec();
+    steps.extend(revised.steps.iter().cloned());
+    PlannerAgentPlan { steps }
+}
 
-    println!("Cleaned Planner AI Response: {}", string_response);
+/// Requests a plan, validating each response with `validate` and feeding the issues back
+/// until it passes or `max_repair_attempts` is exhausted.
+async fn request_valid_plan<F>(
+    base_prompt: String,
+    max_repair_attempts: usize,
+    validate: F,
+) -> Result<PlannerAgentPlan, Box<dyn std::error::Error>>
+where
+    F: Fn(&PlannerAgentPlan) -> Vec<String>,
+{
+    let mut prompt = base_prompt.clone();
+    let mut attempt = 0;
+    loop {
+        let string_response = request_plan(&prompt).await?;
+        debug!("Cleaned Planner AI Response: {}", string_response);
 
-    // Try to parse the plan for validation (optional, can be used by orchestrator)
-    let _plan: Result<PlannerAgentPlan, _> = serde_json::from_str(&string_response);
-    if let Err(e) = &_plan {
-        eprintln!(
-            "Warning: Could not parse planner response as PlannerAgentPlan: {}",
-            e
+        let issues = match serde_json::from_str::<PlannerAgentPlan>(&string_response) {
+            Ok(plan) => {
+                let issues = validate(&plan);
+                if issues.is_empty() {
+                    return Ok(plan);
+                }
+                issues
+            }
+            Err(e) => vec![format!(
+                "The response is not a valid plan JSON object: {}",
+                e
+            )],
+        };
+
+        warn!(
+            "Planner response failed validation (attempt {}):\n- {}",
+            attempt + 1,
+            issues.join("\n- ")
         );
+        if attempt >= max_repair_attempts {
+            return Err(format!(
+                "Planner did not produce a valid plan after {} repair attempt(s):\n- {}",
+                max_repair_attempts,
+                issues.join("\n- ")
+            )
+            .into());
+        }
+        attempt += 1;
+        prompt = gen_repair_prompt(&base_prompt, &string_response, &issues);
     }
+}
+
+/// Sends one planner prompt and strips code block markers from the response.
+async fn request_plan(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = generate_ai_response(prompt, PLANNER_SYSTEM_INSTRUCTIONS)
+        .await
+        .map_err(|e| format!("Error generating planner AI response: {}", e))?;
+
+    Ok(response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+        .to_string())
+}
 
-    Ok(string_response)
+fn gen_repair_prompt(base_prompt: &str, previous_response: &str, issues: &[String]) -> String {
+    format!(
+        "{}\n\nYour previous plan was:\n{}\n\nIt was rejected for these reasons:\n- {}\n\nReturn a corrected plan that fixes every problem listed above. Output only the raw JSON plan.",
+        base_prompt,
+        previous_response,
+        issues.join("\n- ")
+    )
 }
 
-fn gen_prompt(user_task: &str) -> String {
+fn gen_prompt(user_task: &str, known_tools: &[&str]) -> String {
+    let tool_list = known_tools.join(", ");
     format!(
         r#"
 Generate a high-level, step-by-step plan for the orchestrator AI to accomplish the user's request using its browser access and other available tools.
@@ -80,14 +272,14 @@ Instructions for the Planner Agent:
 - For each step, output a JSON object with the following structure:
   - id: String (unique identifier for the agent step, e.g., "ResponseAgent1")
   - parameters: Object containing:
-    - goal: String (the specific goal for this agent step)
+    - goal: String (the specific goal for this agent step, never empty)
     - description: String (a brief description of what this agent does)
-    - tools: String (tools or APIs this agent will use)
+    - tools: String (comma-separated tools this agent will use, chosen only from: {tool_list})
     - role: String (the role or type of agent, e.g., "WebSearchAgent")
     - backstory: String (background or context for the agent, can be brief)
     - context: String (any context or input needed from previous steps, or empty if none)
   - run_in_parallel: Boolean (true if this agent can run in parallel with others)
-  - needs_context_from: Integer or null (index of previous step to get context from, or null)
+  - needs_context_from: Array of step ids (or zero-based step indices) whose outputs this step needs as context, or [] if none. List every step whose results must be combined, e.g. a step that compares or summarizes several research steps should reference all of them.
 - Output the plan as a JSON object: {{"steps": [{{...}}, ...]}}
 - Do NOT output markdown or code block markers, only raw JSON.
 - Example output:
@@ -98,17 +290,100 @@ Instructions for the Planner Agent:
       "parameters": {{
         "goal": "Find the top 3 footballers 2024",
         "description": "Searches the web for the top 3 footballers in 2024.",
-        "tools": "web_search",
+        "tools": "search_query, go_to_url, extract_content",
         "role": "WebSearchAgent",
         "backstory": "An agent skilled at searching the web for sports rankings.",
         "context": ""
       }},
-      "run_in_parallel": false,
-      "needs_context_from": null
+      "run_in_parallel": true,
+      "needs_context_from": []
+    }},
+    {{
+      "id": "ResponseAgent2",
+      "parameters": {{
+        "goal": "Find the top 3 basketball players 2024",
+        "description": "Searches the web for the top 3 basketball players in 2024.",
+        "tools": "search_query, go_to_url, extract_content",
+        "role": "WebSearchAgent",
+        "backstory": "An agent skilled at searching the web for sports rankings.",
+        "context": ""
+      }},
+      "run_in_parallel": true,
+      "needs_context_from": []
     }},
-    ...
+    {{
+      "id": "SummaryAgent",
+      "parameters": {{
+        "goal": "Compare the footballers and basketball players found and summarize the results",
+        "description": "Combines the findings of the research agents into one summary.",
+        "tools": "create_document, done",
+        "role": "SummaryAgent",
+        "backstory": "An agent skilled at comparing and summarizing research.",
+        "context": ""
+      }},
+      "run_in_parallel": false,
+      "needs_context_from": ["ResponseAgent1", "ResponseAgent2"]
+    }}
   ]
 }}
 Now, based on the User Request: {user_task}, generate the plan."#
     )
 }
+
+/// Longest step output quoted back to the planner when re-planning.
+const REPLAN_OUTPUT_LIMIT: usize = 2000;
+
+fn gen_replan_prompt(
+    user_task: &str,
+    finished: &[FinishedStep<'_>],
+    remaining: &[AgentStep],
+    known_tools: &[&str],
+) -> String {
+    let finished_str = finished
+        .iter()
+        .map(|f| match f.result {
+            Ok(output) => {
+                let output: String = output.chars().take(REPLAN_OUTPUT_LIMIT).collect();
+                format!(
+                    "- {} (goal: {}): SUCCEEDED. Output:\n{}",
+                    f.step.id,
+                    f.step.param("goal"),
+                    output
+                )
+            }
+            Err(reason) => format!(
+                "- {} (goal: {}): FAILED. Reason: {}",
+                f.step.id,
+                f.step.param("goal"),
+                reason
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let remaining_str = serde_json::to_string_pretty(remaining).unwrap_or_default();
+    let taken_ids = finished
+        .iter()
+        .map(|f| format!("\"{}\"", f.step.id))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        r#"{base}
+
+The plan above is already being executed and has to be revised.
+
+Steps that have finished:
+{finished_str}
+
+Steps of the current plan that have not started yet:
+{remaining_str}
+
+Revise the remaining part of the plan so the user request can still be accomplished, using what has been learned so far:
+- Do not repeat steps that succeeded; reference their results through needs_context_from using their id.
+- Do not depend on the output of failed steps. Retry failed work with a different approach (other sources, other search queries) if it is still needed.
+- Keep, change, drop or add remaining steps as needed.
+- Use new ids; these ids are already taken: {taken_ids}.
+- Output only the remaining steps as {{"steps": [...]}}. Output {{"steps": []}} if nothing else is needed."#,
+        base = gen_prompt(user_task, known_tools),
+    )
+}
@@ -0,0 +1,327 @@
+use crate::agent::action::{ActionCall, ActionError, ElementTarget};
+use crate::agent::builtin_actions::builtin_handlers;
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, Locator};
+use crate::browser_control::interactive_elements::{InteractiveElement, find_by_id};
+use crate::prompts::AGENT_TASK_PROMPT;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub type ActionResult = Result<String, Box<dyn std::error::Error + Send + Sync>>;
+
+/// What a handler gets to work with when it runs.
+pub struct ActionContext<'a> {
+    pub browser: &'a dyn BrowserBackend,
+    pub agent_id: &'a str,
+    /// The element list shown in the prompt, which element ids refer to.
+    pub elements: &'a [InteractiveElement],
+    /// Directory for files the action produces, e.g. generated documents.
+    pub output_dir: &'a Path,
+    /// Page content the action read, e.g. by `extract_content`. The agent records it in
+    /// the run trace.
+    pub extracted: Mutex<Option<String>>,
+}
+
+impl ActionContext<'_> {
+    pub fn set_extracted(&self, content: impl Into<String>) {
+        *self.extracted.lock().unwrap() = Some(content.into());
+    }
+
+    pub fn take_extracted(&self) -> Option<String> {
+        self.extracted.lock().unwrap().take()
+    }
+
+    /// How `target` is shown to a person: the element's prompt line, or the selector.
+    pub fn describe(&self, target: &ElementTarget) -> String {
+        match target {
+            ElementTarget::Id(id) => find_by_id(self.elements, *id)
+                .map(|element| element.to_string())
+                .unwrap_or_else(|| format!("element {}", id)),
+            ElementTarget::Selector(selector) => selector.clone(),
+        }
+    }
+
+    /// Looks an element id up in [`ActionContext::elements`], or wraps a CSS selector.
+    pub fn locate(&self, target: &ElementTarget) -> BrowserResult<Locator> {
+        match target {
+            ElementTarget::Id(id) => find_by_id(self.elements, *id)
+                .map(|element| element.locator.clone())
+                .ok_or_else(|| format!("No element {} in the element list", id).into()),
+            ElementTarget::Selector(selector) => Ok(Locator::Css(selector.clone())),
+        }
+    }
+}
+
+/// A tool the task agent can choose as its `next_action`.
+///
+/// The name, description and parameter schema are rendered into the task prompt, and the
+/// schema is used to validate the model's parameters before [`ActionHandler::execute`] runs.
+#[async_trait]
+pub trait ActionHandler: Send + Sync {
+    /// Key used in `next_action`, e.g. `"go_to_url"`.
+    fn name(&self) -> &str;
+
+    /// One-line description shown to the model.
+    fn description(&self) -> &str;
+
+    /// JSON Schema (subset) of the parameter object.
+    fn parameters(&self) -> Value;
+
+    /// Checks the parameters before execution. Defaults to validating against
+    /// [`ActionHandler::parameters`].
+    fn validate(&self, params: &Value) -> Result<(), ActionError> {
+        validate_against_schema(&self.parameters(), params, "").map_err(|reason| {
+            ActionError::InvalidParameters {
+                action: self.name().to_string(),
+                reason,
+            }
+        })
+    }
+
+    /// Runs the action. The returned string is the control signal for the agent loop
+    /// (`CONTINUE`, `AGENT_DONE`, `FINAL_ANSWER: ...`, ...).
+    async fn execute(&self, ctx: &ActionContext<'_>, params: &Value) -> ActionResult;
+}
+
+/// The set of actions available to an agent, keyed by name.
+#[derive(Clone, Default)]
+pub struct ActionRegistry {
+    handlers: BTreeMap<String, Arc<dyn ActionHandler>>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        ActionRegistry::default()
+    }
+
+    /// A registry containing the built-in browser and document actions.
+    pub fn with_builtins() -> Self {
+        let mut registry = ActionRegistry::new();
+        for handler in builtin_handlers() {
+            registry.register(handler);
+        }
+        registry
+    }
+
+    /// Adds a handler, replacing any existing handler with the same name.
+    pub fn register(&mut self, handler: Arc<dyn ActionHandler>) -> &mut Self {
+        self.handlers.insert(handler.name().to_string(), handler);
+        self
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<Arc<dyn ActionHandler>> {
+        self.handlers.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn ActionHandler>> {
+        self.handlers.get(name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.handlers.keys().map(String::as_str).collect()
+    }
+
+    /// Resolves the handler for `call` and validates its parameters.
+    pub fn validate(&self, call: &ActionCall) -> Result<&Arc<dyn ActionHandler>, ActionError> {
+        let handler = self.get(&call.name).ok_or_else(|| {
+            ActionError::UnknownAction(format!(
+                "unknown action `{}`; expected one of: {}",
+                call.name,
+                self.names().join(", ")
+            ))
+        })?;
+        handler.validate(&call.params)?;
+        Ok(handler)
+    }
+
+    /// Validates and runs `call`. Validation failures are returned as
+    /// `Ok("ERROR_INVALID_ACTION: ...")` so the agent can report them back to the model;
+    /// only failures during execution are errors.
+    pub async fn dispatch(&self, ctx: &ActionContext<'_>, call: &ActionCall) -> ActionResult {
+        match self.validate(call) {
+            Ok(handler) => handler.execute(ctx, &call.params).await,
+            Err(e) => Ok(format!("ERROR_INVALID_ACTION: {}", e)),
+        }
+    }
+
+    /// Bullet list of the registered actions for the task prompt.
+    pub fn render_tool_list(&self) -> String {
+        self.handlers
+            .values()
+            .map(|handler| format!("- `{}`: {}", handler.name(), handler.description()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The `next_action` entries of the response format, with example parameters
+    /// derived from each schema.
+    pub fn render_action_formats(&self) -> String {
+        self.handlers
+            .values()
+            .map(|handler| {
+                format!(
+                    "    \"{}\": {}",
+                    handler.name(),
+                    example_from_schema(&handler.parameters())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n")
+    }
+
+    /// The full task agent system prompt for this set of actions.
+    pub fn task_prompt(&self) -> String {
+        AGENT_TASK_PROMPT
+            .replace("{tool_list}", &self.render_tool_list())
+            .replace("{action_formats}", &self.render_action_formats())
+    }
+}
+
+/// Builds an example value for a schema: `"..."` for strings, one example item for arrays
+/// and every property for objects. An `enum` is shown as its values joined by `|`, an
+/// `anyOf` as its first alternative.
+pub fn example_from_schema(schema: &Value) -> Value {
+    if let Some(first) = schema["anyOf"]
+        .as_array()
+        .and_then(|options| options.first())
+    {
+        return example_from_schema(first);
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        let options: Vec<&str> = values.iter().filter_map(Value::as_str).collect();
+        return Value::String(options.join("|"));
+    }
+    match schema["type"].as_str() {
+        Some("object") => {
+            let mut example = serde_json::Map::new();
+            if let Some(properties) = schema["properties"].as_object() {
+                for (key, property) in properties {
+                    example.insert(key.clone(), example_from_schema(property));
+                }
+            }
+            Value::Object(example)
+        }
+        Some("array") => json!([example_from_schema(&schema["items"])]),
+        Some("boolean") => json!(true),
+        Some("integer") | Some("number") => json!(schema["minimum"].as_i64().unwrap_or(0)),
+        _ => json!("..."),
+    }
+}
+
+/// Validates `value` against the subset of JSON Schema used by action parameters:
+/// `type`, `properties`, `required`, `additionalProperties: false`, `items`, `enum`,
+/// `anyOf`, `minimum` and `minLength`/`minItems`.
+pub fn validate_against_schema(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let at = |path: &str| {
+        if path.is_empty() {
+            "parameters".to_string()
+        } else {
+            format!("`{}`", path)
+        }
+    };
+
+    if let Some(options) = schema["anyOf"].as_array() {
+        let mut reasons = Vec::new();
+        for option in options {
+            match validate_against_schema(option, value, path) {
+                Ok(()) => return Ok(()),
+                Err(reason) => reasons.push(reason),
+            }
+        }
+        return Err(reasons.join("; or "));
+    }
+
+    if let Some(expected) = schema["type"].as_str() {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "null" => value.is_null(),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("{} must be of type {}", at(path), expected));
+        }
+    }
+
+    if let Some(options) = schema["enum"].as_array()
+        && !options.contains(value)
+    {
+        return Err(format!(
+            "{} must be one of {}",
+            at(path),
+            Value::Array(options.clone())
+        ));
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema["properties"].as_object();
+            if let Some(required) = schema["required"].as_array() {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        return Err(format!("missing required parameter `{}`", join(path, key)));
+                    }
+                }
+            }
+            for (key, item) in map {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) => validate_against_schema(property, item, &join(path, key))?,
+                    None if schema["additionalProperties"] == json!(false) => {
+                        return Err(format!("unexpected parameter `{}`", join(path, key)));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema["minItems"].as_u64()
+                && (items.len() as u64) < min
+            {
+                return Err(format!(
+                    "{} must contain at least {} item(s)",
+                    at(path),
+                    min
+                ));
+            }
+            if !schema["items"].is_null() {
+                for (index, item) in items.iter().enumerate() {
+                    validate_against_schema(
+                        &schema["items"],
+                        item,
+                        &format!("{}[{}]", path, index),
+                    )?;
+                }
+            }
+        }
+        Value::Number(n) => {
+            if let (Some(min), Some(n)) = (schema["minimum"].as_f64(), n.as_f64())
+                && n < min
+            {
+                return Err(format!("{} must be at least {}", at(path), min));
+            }
+        }
+        Value::String(s) => {
+            if let Some(min) = schema["minLength"].as_u64()
+                && (s.trim().chars().count() as u64) < min
+            {
+                return Err(format!("{} must not be empty", at(path)));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -1,36 +1,40 @@
-use crate::prompts::AGENT_TASK_PROMPT;
-use project_oculus::utils::generate_ai_response;
+use crate::llm::image::ImagePart;
+use crate::llm::provider::LlmResponse;
+use crate::utils::generate_ai_response_with_images;
 use serde::{Deserialize, Serialize}; // Added Deserialize for completeness, though not strictly used for adding
 use std::error::Error;
-use std::sync::{Mutex, OnceLock};
-// Function to handle the task agent logic
-pub async fn task_agent(_last_step: String) -> Result<String, Box<dyn Error>> {
-    let task = format!("{}{}", AGENT_TASK_PROMPT, _last_step);
-    let response = generate_ai_response(&task, "").await;
-    let mut string_response: String = String::new();
+use tracing::{debug, trace};
 
-    match response {
-        Ok(ai_response) => {
-            println!("AI Response: {}", ai_response);
-            string_response = ai_response;
-        }
-        Err(e) => {
-            eprintln!("Error generating AI response: {}", e);
-        }
-    }
+/// Asks the model for the task agent's next action.
+/// `task_prompt` is the rendered tool prompt, see `ActionRegistry::task_prompt`.
+/// `images` (the annotated page screenshot) are attached after the text.
+/// The returned text has code fences and newlines stripped; `usage` is passed through
+/// for tracing. Provider errors are returned as they are.
+pub async fn task_agent(
+    _last_step: String,
+    task_prompt: &str,
+    images: &[ImagePart],
+) -> Result<LlmResponse, Box<dyn Error>> {
+    let task = format!("{}{}", task_prompt, _last_step);
+    let ai_response = generate_ai_response_with_images(&task, images, "")
+        .await
+        .map_err(|e| e as Box<dyn Error>)?;
+    trace!("AI Response: {}", ai_response.text);
 
     // Parse the AI response to remove ````json` and `\n`
-    string_response = string_response
+    let string_response = ai_response
+        .text
         .replace("```json", "")
         .replace("```", "")
         .replace("\n", "");
-    println!("Parsed AI Response: {}", string_response);
+    debug!("Parsed AI Response: {}", string_response);
 
-    Ok(string_response)
+    Ok(LlmResponse {
+        text: string_response,
+        usage: ai_response.usage,
+    })
 }
 
-static TASK_HISTORY: OnceLock<Mutex<Vec<String>>> = OnceLock::new();
-
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Task {
     id: u32,
@@ -38,24 +42,3 @@ pub struct Task {
     status: String,
     // You can add more fields like timestamp, user, etc.
 }
-
-pub async fn get_task_history() -> Result<Vec<String>, Box<dyn Error>> {
-    if let Some(history) = TASK_HISTORY.get() {
-        let tasks = history.lock().unwrap();
-        Ok(tasks.clone())
-    } else {
-        let initial_history = Mutex::new(Vec::new());
-        TASK_HISTORY.set(initial_history).unwrap();
-        Ok(Vec::new())
-    }
-}
-
-pub async fn add_task_to_history(task: String) -> Result<(), Box<dyn Error>> {
-    if let Some(history) = TASK_HISTORY.get() {
-        let mut tasks = history.lock().unwrap();
-        tasks.push(task);
-        Ok(())
-    } else {
-        Err("Task history not initialized".into())
-    }
-}
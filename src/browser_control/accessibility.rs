@@ -0,0 +1,416 @@
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, Locator};
+use crate::browser_control::interactive_elements::InteractiveElement;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::fmt::Write;
+
+/// How the agent sees the page in its prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationMode {
+    /// The flat, numbered list of interactive elements.
+    #[default]
+    Elements,
+    /// The page's accessibility tree: landmarks, headings, groups and text around the
+    /// numbered interactive elements.
+    AccessibilityTree,
+}
+
+impl std::str::FromStr for ObservationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "elements" => Ok(ObservationMode::Elements),
+            "accessibility_tree" | "accessibility" | "a11y" => {
+                Ok(ObservationMode::AccessibilityTree)
+            }
+            other => Err(format!("Unknown observation mode: {}", other)),
+        }
+    }
+}
+
+/// Read from the `[observation]` table of the config file:
+///
+/// ```toml
+/// [observation]
+/// mode = "accessibility_tree"
+/// token_budget = 3000
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObservationConfig {
+    pub mode: ObservationMode,
+    /// Rough upper bound, in tokens, on the accessibility tree in a prompt. Text is dropped
+    /// first, then the end of the tree.
+    pub token_budget: usize,
+}
+
+impl Default for ObservationConfig {
+    fn default() -> Self {
+        ObservationConfig {
+            mode: ObservationMode::default(),
+            token_budget: 3000,
+        }
+    }
+}
+
+/// A node of the accessibility tree collected by [`accessibility_tree`]. Elements without
+/// a role (plain `div`s and `span`s) are left out and their children moved up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxNode {
+    /// ARIA role, or `text` for text between elements.
+    pub role: String,
+    pub name: Option<String>,
+    /// Id of the matching [`InteractiveElement`], for nodes the agent can act on.
+    pub element: Option<usize>,
+    /// Heading level.
+    pub level: Option<u8>,
+    pub value: Option<String>,
+    /// `checked`, `disabled`, `expanded`, `required`, ...
+    pub states: Vec<String>,
+    pub children: Vec<AxNode>,
+}
+
+/// Builds the tree in one pass over the DOM. `arguments[0]` lists `[id, selector]` of the
+/// interactive elements so their nodes can carry the same numbers.
+const ACCESSIBILITY_TREE_JS: &str = r#"
+    const MAX_TEXT = 120;
+    const MAX_NODES = 5000;
+    const squash = (value) => (value || '').trim().replace(/\s+/g, ' ');
+    const clip = (value) => value.length > MAX_TEXT ? value.slice(0, MAX_TEXT) + '…' : value;
+    const ids = new Map();
+    for (const [id, selector] of arguments[0]) {
+        try {
+            const element = document.querySelector(selector);
+            if (element) ids.set(element, id);
+        } catch (e) {}
+    }
+    const SKIPPED = ['script', 'style', 'noscript', 'template', 'head', 'meta', 'link', 'iframe'];
+    const IMPLICIT_ROLES = {
+        nav: 'navigation', main: 'main', aside: 'complementary', form: 'form', dialog: 'dialog',
+        search: 'search', article: 'article', button: 'button', textarea: 'textbox',
+        ul: 'list', ol: 'list', li: 'listitem', table: 'table', tr: 'row', th: 'columnheader',
+        td: 'cell', fieldset: 'group', details: 'group', summary: 'button', option: 'option',
+        progress: 'progressbar', img: 'img',
+    };
+    const INPUT_ROLES = {
+        button: 'button', submit: 'button', reset: 'button', image: 'button',
+        checkbox: 'checkbox', radio: 'radio', range: 'slider', search: 'searchbox',
+    };
+    // Roles whose name is their text content; their own text isn't repeated as children.
+    const NAME_FROM_CONTENT = ['button', 'link', 'heading', 'tab', 'menuitem', 'menuitemcheckbox',
+        'menuitemradio', 'option', 'treeitem', 'cell', 'columnheader', 'checkbox', 'radio', 'switch'];
+    const sectioned = (element) => element.parentElement
+        && element.parentElement.closest('article, aside, main, nav, section');
+    function roleOf(element, tag) {
+        const explicit = element.getAttribute('role');
+        if (explicit) return explicit.split(' ')[0];
+        if (/^h[1-6]$/.test(tag)) return 'heading';
+        if (tag === 'a') return element.hasAttribute('href') ? 'link' : null;
+        if (tag === 'header') return sectioned(element) ? null : 'banner';
+        if (tag === 'footer') return sectioned(element) ? null : 'contentinfo';
+        if (tag === 'section') {
+            return element.hasAttribute('aria-label') || element.hasAttribute('aria-labelledby')
+                ? 'region' : null;
+        }
+        if (tag === 'select') return element.multiple ? 'listbox' : 'combobox';
+        if (tag === 'input') {
+            return INPUT_ROLES[(element.getAttribute('type') || 'text').toLowerCase()] || 'textbox';
+        }
+        if (tag === 'img' && element.getAttribute('alt') === '') return null;
+        return IMPLICIT_ROLES[tag] || null;
+    }
+    function nameOf(element, tag, role) {
+        const labelledBy = element.getAttribute('aria-labelledby');
+        if (labelledBy) {
+            const name = squash(labelledBy.split(/\s+/)
+                .map(id => document.getElementById(id))
+                .filter(Boolean)
+                .map(node => node.innerText || node.textContent)
+                .join(' '));
+            if (name) return name;
+        }
+        const label = squash(element.getAttribute('aria-label'));
+        if (label) return label;
+        if (element.labels && element.labels.length) {
+            const name = squash(Array.from(element.labels).map(l => l.innerText || l.textContent).join(' '));
+            if (name) return name;
+        }
+        if (tag === 'fieldset') {
+            const legend = element.querySelector(':scope > legend');
+            if (legend && squash(legend.textContent)) return squash(legend.textContent);
+        }
+        if (tag === 'table' && element.caption && squash(element.caption.textContent)) {
+            return squash(element.caption.textContent);
+        }
+        const type = (element.getAttribute('type') || '').toLowerCase();
+        if (tag === 'input' && ['button', 'submit', 'reset'].includes(type)) {
+            return squash(element.value);
+        }
+        if (NAME_FROM_CONTENT.includes(role) && !['input', 'select', 'textarea'].includes(tag)) {
+            const text = squash(element.innerText || element.textContent);
+            if (text) return text;
+        }
+        for (const attribute of ['alt', 'title', 'placeholder']) {
+            const value = squash(element.getAttribute(attribute));
+            if (value) return value;
+        }
+        return '';
+    }
+    function valueOf(element, tag) {
+        if (tag === 'select') {
+            return Array.from(element.selectedOptions || []).map(o => squash(o.text)).join(', ');
+        }
+        if (tag === 'textarea') return element.value;
+        if (tag === 'input') {
+            const type = (element.getAttribute('type') || 'text').toLowerCase();
+            if (['button', 'submit', 'reset', 'image', 'checkbox', 'radio'].includes(type)) return null;
+            return type === 'password' && element.value ? '***' : element.value;
+        }
+        if (element.isContentEditable) return squash(element.innerText);
+        return null;
+    }
+    function statesOf(element, tag) {
+        const states = [];
+        const type = (element.getAttribute('type') || '').toLowerCase();
+        let checked = element.getAttribute('aria-checked');
+        if (tag === 'input' && (type === 'checkbox' || type === 'radio')) checked = String(element.checked);
+        if (checked === 'true') states.push('checked');
+        if (checked === 'false') states.push('unchecked');
+        if (element.disabled || element.getAttribute('aria-disabled') === 'true') states.push('disabled');
+        const expanded = element.getAttribute('aria-expanded');
+        if (tag === 'details') states.push(element.open ? 'expanded' : 'collapsed');
+        else if (expanded === 'true') states.push('expanded');
+        else if (expanded === 'false') states.push('collapsed');
+        if (element.getAttribute('aria-selected') === 'true' || (tag === 'option' && element.selected)) {
+            states.push('selected');
+        }
+        if (element.getAttribute('aria-pressed') === 'true') states.push('pressed');
+        if (element.required || element.getAttribute('aria-required') === 'true') states.push('required');
+        if (element.readOnly || element.getAttribute('aria-readonly') === 'true') states.push('readonly');
+        if (element.getAttribute('aria-invalid') === 'true') states.push('invalid');
+        if (element.getAttribute('aria-current') && element.getAttribute('aria-current') !== 'false') {
+            states.push('current');
+        }
+        if (document.activeElement === element && element !== document.body) states.push('focused');
+        return states;
+    }
+    function isHidden(element) {
+        if (element.hidden || element.getAttribute('aria-hidden') === 'true') return true;
+        const style = window.getComputedStyle(element);
+        return style.display === 'none' || style.visibility === 'hidden';
+    }
+    let count = 0;
+    // Returns the nodes for `node`: one for an element with a role, its children's nodes
+    // for one without. `textInName` is set below elements named by their content.
+    function walk(node, textInName) {
+        if (count >= MAX_NODES) return [];
+        if (node.nodeType === Node.TEXT_NODE) {
+            const text = squash(node.textContent);
+            if (!text || textInName) return [];
+            count++;
+            return [{ role: 'text', name: clip(text) }];
+        }
+        if (node.nodeType !== Node.ELEMENT_NODE) return [];
+        const tag = node.tagName.toLowerCase();
+        if (SKIPPED.includes(tag) || isHidden(node)) return [];
+        const role = roleOf(node, tag);
+        const element = ids.get(node);
+        const childTextInName = textInName || NAME_FROM_CONTENT.includes(role);
+        const children = [];
+        for (const child of (node.shadowRoot || node).childNodes) {
+            children.push(...walk(child, childTextInName));
+        }
+        if (!role && element === undefined) return children;
+        if (role === 'presentation' || role === 'none') return children;
+        count++;
+        const result = { role: role || tag, name: clip(nameOf(node, tag, role)), states: statesOf(node, tag), children };
+        if (element !== undefined) result.element = element;
+        if (role === 'heading') {
+            result.level = Number(node.getAttribute('aria-level')) || Number(tag.slice(1)) || 2;
+        }
+        const value = valueOf(node, tag);
+        if (value !== null && value !== undefined) result.value = clip(String(value));
+        return [result];
+    }
+    return document.body ? walk(document.body, false) : [];
+"#;
+
+/// Collects the accessibility tree of the current page with one script call. Nodes of the
+/// `elements` carry their ids.
+pub async fn accessibility_tree(
+    browser: &dyn BrowserBackend,
+    elements: &[InteractiveElement],
+) -> BrowserResult<Vec<AxNode>> {
+    let selectors: Vec<Value> = elements
+        .iter()
+        .filter_map(|element| match &element.locator {
+            Locator::Css(selector) => Some(json!([element.id, selector])),
+            Locator::Id(id) => Some(json!([element.id, format!("[id=\"{}\"]", id)])),
+            Locator::XPath(_) => None,
+        })
+        .collect();
+    let tree = browser
+        .execute_script(ACCESSIBILITY_TREE_JS, vec![Value::Array(selectors)])
+        .await?;
+    Ok(serde_json::from_value(tree)?)
+}
+
+/// Rough characters per token, for [`render_tree`]'s budget.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// One rendered node.
+struct Line {
+    depth: usize,
+    text: String,
+    is_text: bool,
+    /// The node or one of its descendants is a numbered element. These lines are never
+    /// left out.
+    interactive: bool,
+    /// Index after the node's last descendant.
+    end: usize,
+}
+
+impl Line {
+    fn len(&self) -> usize {
+        self.depth * 2 + self.text.len() + 1
+    }
+}
+
+/// Renders the tree as an indented outline, one node per line, e.g.
+///
+/// ```text
+/// - navigation "Main"
+///   - [1] link "Home"
+/// - heading "Sign in" level=1
+/// - form
+///   - [2] textbox "Email" value="" required
+/// ```
+///
+/// If the outline exceeds `token_budget`, it is pruned until it fits: text nodes go first,
+/// then the largest subtrees without numbered elements (long lists, table cells) are
+/// collapsed into their root, then the remaining nodes without numbered elements are left
+/// out from the end. Numbered elements and the nodes around them are always kept, even if
+/// that exceeds the budget.
+pub fn render_tree(nodes: &[AxNode], token_budget: usize) -> String {
+    let mut lines = Vec::new();
+    flatten(nodes, 0, &mut lines);
+    let budget = token_budget.saturating_mul(CHARS_PER_TOKEN);
+    let mut visible = vec![true; lines.len()];
+    let mut collapsed = vec![0; lines.len()];
+    let mut used: usize = lines.iter().map(Line::len).sum();
+
+    if used > budget {
+        for (index, line) in lines.iter().enumerate() {
+            if line.is_text {
+                visible[index] = false;
+                used -= line.len();
+            }
+        }
+    }
+
+    if used > budget {
+        let mut candidates: Vec<(usize, usize)> = lines
+            .iter()
+            .enumerate()
+            .filter(|(index, line)| !line.interactive && visible[*index])
+            .map(|(index, line)| {
+                let shown = (index + 1..line.end).filter(|&i| visible[i]).count();
+                (index, shown)
+            })
+            .filter(|(_, shown)| *shown > 0)
+            .collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (index, _) in candidates {
+            if used <= budget {
+                break;
+            }
+            // An earlier, larger candidate may already have hidden this one
+            if !visible[index] {
+                continue;
+            }
+            for descendant in index + 1..lines[index].end {
+                if visible[descendant] {
+                    visible[descendant] = false;
+                    used -= lines[descendant].len();
+                    collapsed[index] += 1;
+                }
+            }
+        }
+    }
+
+    let mut left_out = 0;
+    if used > budget {
+        for index in (0..lines.len()).rev() {
+            if used <= budget {
+                break;
+            }
+            if visible[index] && !lines[index].interactive {
+                visible[index] = false;
+                used -= lines[index].len();
+                left_out += 1 + collapsed[index];
+                collapsed[index] = 0;
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if !visible[index] {
+            continue;
+        }
+        let _ = write!(output, "{}{}", "  ".repeat(line.depth), line.text);
+        if collapsed[index] > 0 {
+            let _ = write!(output, " ({} nested node(s) left out)", collapsed[index]);
+        }
+        output.push('\n');
+    }
+    if left_out > 0 {
+        let _ = writeln!(
+            output,
+            "… {} more node(s) left out to fit the page into the prompt",
+            left_out
+        );
+    }
+    output.trim_end().to_string()
+}
+
+/// Appends `nodes` and their descendants to `lines` in document order. Returns whether any
+/// of them is a numbered element.
+fn flatten(nodes: &[AxNode], depth: usize, lines: &mut Vec<Line>) -> bool {
+    let mut any_interactive = false;
+    for node in nodes {
+        let mut text = String::from("- ");
+        if let Some(element) = node.element {
+            let _ = write!(text, "[{}] ", element);
+        }
+        text.push_str(&node.role);
+        if let Some(name) = node.name.as_deref().filter(|name| !name.is_empty()) {
+            let _ = write!(text, " {:?}", name);
+        }
+        if let Some(level) = node.level {
+            let _ = write!(text, " level={}", level);
+        }
+        if let Some(value) = &node.value {
+            let _ = write!(text, " value={:?}", value);
+        }
+        for state in &node.states {
+            let _ = write!(text, " {}", state);
+        }
+        let index = lines.len();
+        lines.push(Line {
+            depth,
+            text,
+            is_text: node.role == "text",
+            interactive: false,
+            end: index + 1,
+        });
+        let interactive = flatten(&node.children, depth + 1, lines) || node.element.is_some();
+        lines[index].interactive = interactive;
+        lines[index].end = lines.len();
+        any_interactive |= interactive;
+    }
+    any_interactive
+}
@@ -1,18 +1,18 @@
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, Locator};
 use crate::utils::generate_ai_response;
 use std::fs;
 use std::io::{self, Write};
 use std::path::Path;
-use thirtyfour::prelude::*;
-pub async fn go_to_url(driver: &WebDriver, url: &str) -> WebDriverResult<()> {
-    print!("Navigating to URL: {}", url);
-    driver.new_tab().await?;
-    driver.goto(url).await?;
+use tracing::{debug, info, warn};
+pub async fn go_to_url(browser: &dyn BrowserBackend, url: &str) -> BrowserResult<()> {
+    debug!("Navigating to URL: {}", url);
+    browser.goto(url).await?;
     Ok(())
 }
 
-pub async fn extract_content(driver: &WebDriver) -> WebDriverResult<String> {
-    print!("Extracting content from the current page.");
-    let content = driver.find(By::Tag("body")).await?.text().await?;
+pub async fn extract_content(browser: &dyn BrowserBackend) -> BrowserResult<String> {
+    debug!("Extracting content from the current page.");
+    let content = browser.page_text().await?;
 
     // Generate AI summary of the page content
     let prompt = format!(
@@ -22,39 +22,40 @@ pub async fn extract_content(driver: &WebDriver) -> WebDriverResult<String> {
 
     match generate_ai_response(&prompt, "").await {
         Ok(summary) => {
-            println!("Page Summary: {}", summary);
+            debug!("Page summary: {}", summary);
             Ok(format!("{}\n\nSummary: {}", content, summary))
         }
         Err(e) => {
-            eprintln!("Error generating summary: {}", e);
+            warn!("Error generating summary: {}", e);
             Ok(content)
         }
     }
 }
 
-pub async fn click_element(driver: &WebDriver, selector: &str) -> WebDriverResult<()> {
-    print!("Clicking element with selector: {}", selector);
-    let element = driver.find(By::Css(selector)).await?;
-    element.click().await?;
+pub async fn click_element(browser: &dyn BrowserBackend, locator: &Locator) -> BrowserResult<()> {
+    debug!("Clicking element: {}", locator);
+    browser.click(locator).await?;
     Ok(())
 }
 
-pub async fn fill_form(driver: &WebDriver, form_data: &[(String, String)]) -> WebDriverResult<()> {
-    print!("Filling form with provided data.");
-    for (selector, value) in form_data {
-        let element = driver.find(By::Css(selector)).await?;
-        element.send_keys(value).await?;
+pub async fn fill_form(
+    browser: &dyn BrowserBackend,
+    form_data: &[(Locator, String)],
+) -> BrowserResult<()> {
+    debug!("Filling form with provided data.");
+    for (locator, value) in form_data {
+        browser.type_text(locator, value).await?;
     }
     Ok(())
 }
 
 pub async fn extract_information(
-    driver: &WebDriver,
+    browser: &dyn BrowserBackend,
     _current_state: String,
-) -> WebDriverResult<String> {
-    print!("Analyzing the current page.");
+) -> BrowserResult<String> {
+    debug!("Analyzing the current page.");
 
-    let content = driver.find(By::Tag("body")).await?.text().await?;
+    let content = browser.page_text().await?;
 
     let prompt = format!(
         "Analyze the current page and extract relevant information given the current task and the body of the content: {}, {}. I want you to give the answer in the following format: ```json {{
@@ -75,110 +76,140 @@ pub async fn extract_information(
                 .replace("```json", "")
                 .replace("```", "")
                 .replace("\n", "");
-            println!("AI Response: {}", cleaned_response);
-            return Ok(cleaned_response);
+            debug!("AI response: {}", cleaned_response);
+            Ok(cleaned_response)
         }
         Err(e) => {
-            eprintln!("Error generating AI response: {}", e);
-            return Ok("Error: Failed to generate AI response".to_string());
+            warn!("Error generating AI response: {}", e);
+            Ok("Error: Failed to generate AI response".to_string())
         }
     }
 }
 
-pub async fn search_query(driver: &WebDriver, _search_term: String) -> WebDriverResult<()> {
-    print!("Searching for: {}", _search_term);
+pub async fn search_query(browser: &dyn BrowserBackend, _search_term: String) -> BrowserResult<()> {
+    debug!("Searching for: {}", _search_term);
     let encoded_term = urlencoding::encode(&_search_term);
     let search_url = format!("https://duckduckgo.com/?q={}", encoded_term);
-    driver.goto(&search_url).await?;
+    browser.goto(&search_url).await?;
     Ok(())
 }
 
-pub async fn go_back(driver: &WebDriver) -> WebDriverResult<()> {
-    print!("Going back to the previous page.");
-    driver.back().await?;
+pub async fn go_back(browser: &dyn BrowserBackend) -> BrowserResult<()> {
+    debug!("Going back to the previous page.");
+    browser.back().await?;
     Ok(())
 }
 
 pub async fn fill_form_with_data(
-    driver: &WebDriver,
+    browser: &dyn BrowserBackend,
     form_data: &[(String, String)],
-) -> WebDriverResult<()> {
-    print!("Filling form with provided data.");
+) -> BrowserResult<()> {
+    debug!("Filling form with provided data.");
     for (selector, value) in form_data {
-        let element = driver.find(By::Css(selector)).await?;
-        element.send_keys(value).await?;
+        browser
+            .type_text(&Locator::Css(selector.to_string()), value)
+            .await?;
     }
 
     Ok(())
 }
 
+/// Prompts on the terminal for each field, shown by its label, and types the answer in.
+/// Fails if stdin is closed rather than typing empty values.
 pub async fn fill_form_with_user_input_credentials(
-    driver: &WebDriver,
-    input_cred_selector: &[(String)],
-) -> WebDriverResult<()> {
-    print!("Filling form with user-provided data.");
-
-    for selector in input_cred_selector {
-        print!("Enter value for {}: ", selector);
-        io::stdout().flush().unwrap(); // Ensure the prompt is displayed immediately
+    browser: &dyn BrowserBackend,
+    fields: &[(String, Locator)],
+) -> BrowserResult<()> {
+    debug!("Filling form with user-provided data.");
+
+    for (label, locator) in fields {
+        print!("Enter value for {}: ", label);
+        io::stdout().flush()?; // Ensure the prompt is displayed immediately
         let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+        if io::stdin().read_line(&mut input)? == 0 {
+            return Err(format!("No input available for {}: stdin is closed", label).into());
+        }
         let value = input.trim();
 
-        let element = driver.find(By::Css(selector)).await?;
-        element.send_keys(value).await?;
+        browser.type_text(locator, value).await?;
     }
     Ok(())
 }
 
+/// Why `filename` can't be used as a document name inside the output directory, if it
+/// can't: it must be a single plain file name, so the write can't land outside it.
+pub fn document_filename_problem(filename: &str) -> Option<&'static str> {
+    if filename.contains(['/', '\\']) {
+        Some("`filename` must not contain path separators")
+    } else if filename.contains("..") {
+        Some("`filename` must not contain `..`")
+    } else if filename.contains(':') {
+        Some("`filename` must not contain a drive prefix or `:`")
+    } else if filename.contains('\0') {
+        Some("`filename` must not contain NUL characters")
+    } else if Path::new(filename).has_root() || Path::new(filename).is_absolute() {
+        Some("`filename` must be a plain file name, not a path")
+    } else {
+        None
+    }
+}
+
 pub async fn create_document(
-    _driver: &WebDriver,
+    _browser: &dyn BrowserBackend,
+    output_dir: &Path,
     filename: &str,
     content: &str,
     format_type: &str,
-) -> WebDriverResult<String> {
-    println!(
+) -> BrowserResult<String> {
+    debug!(
         "Creating document: {} with format: {}",
         filename, format_type
     );
 
-    // Ensure the documents directory exists
-    let documents_dir = "documents";
-    if !Path::new(documents_dir).exists() {
-        if let Err(e) = fs::create_dir_all(documents_dir) {
-            eprintln!("Failed to create documents directory: {}", e);
-            return Ok(format!(
-                "Error: Failed to create documents directory: {}",
-                e
-            ));
-        }
+    if let Some(problem) = document_filename_problem(filename) {
+        warn!("Refusing to write document {:?}: {}", filename, problem);
+        return Ok(format!("Error: {}", problem));
+    }
+
+    // Ensure the output directory exists
+    if !output_dir.exists()
+        && let Err(e) = fs::create_dir_all(output_dir)
+    {
+        warn!("Failed to create output directory: {}", e);
+        return Ok(format!("Error: Failed to create output directory: {}", e));
     }
 
-    let file_path = match format_type.to_lowercase().as_str() {
-        "markdown" | "md" => format!("{}/{}.md", documents_dir, filename),
-        "text" | "txt" => format!("{}/{}.txt", documents_dir, filename),
-        "json" => format!("{}/{}.json", documents_dir, filename),
-        "html" => format!("{}/{}.html", documents_dir, filename),
-        _ => format!("{}/{}.txt", documents_dir, filename), // Default to txt
+    let extension = match format_type.to_lowercase().as_str() {
+        "markdown" | "md" => "md",
+        "text" | "txt" => "txt",
+        "json" => "json",
+        "html" => "html",
+        _ => "txt", // Default to txt
     };
+    let file_path = output_dir.join(format!("{}.{}", filename, extension));
 
     // Write content to file
     if let Err(e) = fs::write(&file_path, content) {
-        eprintln!("Failed to write file {}: {}", file_path, e);
-        return Ok(format!("Error: Failed to write file {}: {}", file_path, e));
+        warn!("Failed to write file {}: {}", file_path.display(), e);
+        return Ok(format!(
+            "Error: Failed to write file {}: {}",
+            file_path.display(),
+            e
+        ));
     }
 
-    println!("Document saved successfully: {}", file_path);
-    Ok(format!("Document saved: {}", file_path))
+    info!("Document saved successfully: {}", file_path.display());
+    Ok(format!("Document saved: {}", file_path.display()))
 }
 
 pub async fn generate_and_save_document(
-    _driver: &WebDriver,
+    _browser: &dyn BrowserBackend,
+    output_dir: &Path,
     task_description: &str,
     filename: &str,
     format_type: &str,
-) -> WebDriverResult<String> {
-    println!("Generating document for task: {}", task_description);
+) -> BrowserResult<String> {
+    debug!("Generating document for task: {}", task_description);
 
     let prompt = match format_type.to_lowercase().as_str() {
         "markdown" | "md" => format!(
@@ -234,10 +265,17 @@ pub async fn generate_and_save_document(
                     generated_content
                 };
 
-            create_document(_driver, filename, &cleaned_content, format_type).await
+            create_document(
+                _browser,
+                output_dir,
+                filename,
+                &cleaned_content,
+                format_type,
+            )
+            .await
         }
         Err(e) => {
-            eprintln!("Error generating document content: {}", e);
+            warn!("Error generating document content: {}", e);
             Ok(format!("Error: Failed to generate document: {}", e))
         }
     }
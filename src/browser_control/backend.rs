@@ -0,0 +1,298 @@
+use crate::browser_control::capabilities::BrowserCapabilities;
+use crate::browser_control::playwright_backend::PlaywrightBackend;
+use crate::browser_control::webdriver_backend::WebDriverBackend;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+pub type BrowserResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// How an element is found on the page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Locator {
+    Css(String),
+    XPath(String),
+    Id(String),
+}
+
+impl Locator {
+    /// `(kind, value)` as understood by [`RESOLVE_LOCATOR_JS`].
+    pub fn to_json(&self) -> Value {
+        match self {
+            Locator::Css(css) => json!(["css", css]),
+            Locator::XPath(xpath) => json!(["xpath", xpath]),
+            Locator::Id(id) => json!(["id", id]),
+        }
+    }
+
+    /// The locator in Playwright selector syntax.
+    pub fn to_selector(&self) -> String {
+        match self {
+            Locator::Css(css) => css.clone(),
+            Locator::XPath(xpath) => format!("xpath={}", xpath),
+            Locator::Id(id) => {
+                format!("[id=\"{}\"]", id.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Locator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Locator::Css(css) => write!(f, "css={}", css),
+            Locator::XPath(xpath) => write!(f, "xpath={}", xpath),
+            Locator::Id(id) => write!(f, "id={}", id),
+        }
+    }
+}
+
+/// What [`BrowserBackend::find_elements`] reports about each matching element.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ElementInfo {
+    pub tag: String,
+    pub text: String,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub visible: bool,
+    /// Absolute XPath of the element, or `//*[@id="..."]` if it has an id.
+    pub xpath: Option<String>,
+}
+
+/// A browser cookie in a form both backends can read and write.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    /// Unix time in seconds; `None` for session cookies.
+    pub expires: Option<i64>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// `Strict`, `Lax` or `None`.
+    pub same_site: Option<String>,
+}
+
+/// Defines `resolveLocator([kind, value])`, returning the matching elements as an array.
+pub const RESOLVE_LOCATOR_JS: &str = r#"
+    function resolveLocator(locator) {
+        const [kind, value] = locator;
+        if (kind === 'xpath') {
+            const snapshot = document.evaluate(value, document, null,
+                XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+            const found = [];
+            for (let i = 0; i < snapshot.snapshotLength; i++) found.push(snapshot.snapshotItem(i));
+            return found;
+        }
+        if (kind === 'id') {
+            return Array.from(document.querySelectorAll('[id="' + CSS.escape(value) + '"]'));
+        }
+        return Array.from(document.querySelectorAll(value));
+    }
+"#;
+
+const FIND_ELEMENTS_JS: &str = r#"
+    function xpathOf(element) {
+        if (element && element.id) { return '//*[@id="' + element.id + '"]'; }
+        const paths = [];
+        for (; element && element.nodeType == Node.ELEMENT_NODE; element = element.parentNode) {
+            let index = 0;
+            for (let sibling = element.previousSibling; sibling; sibling = sibling.previousSibling) {
+                if (sibling.nodeType == Node.DOCUMENT_TYPE_NODE) continue;
+                if (sibling.nodeName == element.nodeName) ++index;
+            }
+            paths.splice(0, 0, element.nodeName.toLowerCase() + (index ? "[" + (index + 1) + "]" : ""));
+        }
+        return paths.length ? "/" + paths.join("/") : null;
+    }
+    function isVisible(element) {
+        const style = window.getComputedStyle(element);
+        if (style.visibility === 'hidden' || style.display === 'none') return false;
+        const rect = element.getBoundingClientRect();
+        return rect.width > 0 && rect.height > 0;
+    }
+    return resolveLocator(arguments[0]).map(element => ({
+        tag: element.tagName.toLowerCase(),
+        text: (element.innerText || '').trim(),
+        id: element.getAttribute('id'),
+        name: element.getAttribute('name'),
+        visible: isVisible(element),
+        xpath: xpathOf(element),
+    }));
+"#;
+
+/// A browser session the agent drives. Implemented over WebDriver
+/// ([`WebDriverBackend`]) and over Playwright, which can attach through CDP
+/// ([`PlaywrightBackend`]).
+///
+/// Scripts passed to [`BrowserBackend::execute_script`] are function bodies: arguments are
+/// available as `arguments[i]` and the value of `return` is returned as JSON.
+#[async_trait]
+pub trait BrowserBackend: Send + Sync {
+    /// Short backend name for logs, e.g. `"webdriver"`.
+    fn name(&self) -> &str;
+
+    async fn goto(&self, url: &str) -> BrowserResult<()>;
+
+    async fn back(&self) -> BrowserResult<()>;
+
+    async fn current_url(&self) -> BrowserResult<String>;
+
+    /// Clicks the first element matching `locator`.
+    async fn click(&self, locator: &Locator) -> BrowserResult<()>;
+
+    /// Types `text` into the first element matching `locator`.
+    async fn type_text(&self, locator: &Locator, text: &str) -> BrowserResult<()>;
+
+    async fn execute_script(&self, script: &str, args: Vec<Value>) -> BrowserResult<Value>;
+
+    /// PNG screenshot of the visible part of the current tab.
+    async fn screenshot_png(&self) -> BrowserResult<Vec<u8>>;
+
+    /// Opens a blank tab and switches to it.
+    async fn new_tab(&self) -> BrowserResult<()>;
+
+    async fn tab_count(&self) -> BrowserResult<usize>;
+
+    /// Switches to the tab at `index`, in the order the tabs were opened.
+    async fn switch_tab(&self, index: usize) -> BrowserResult<()>;
+
+    /// Closes the current tab and switches to the last remaining one.
+    async fn close_tab(&self) -> BrowserResult<()>;
+
+    /// Ends the session. The backend must not be used afterwards.
+    async fn quit(&self) -> BrowserResult<()>;
+
+    /// Cookies of the session. WebDriver only returns the current page's cookies.
+    async fn cookies(&self) -> BrowserResult<Vec<StoredCookie>>;
+
+    /// Adds cookies to the session. WebDriver only accepts cookies for the domain of the
+    /// current page.
+    async fn add_cookies(&self, cookies: &[StoredCookie]) -> BrowserResult<()>;
+
+    /// The `localStorage` entries of the current page's origin.
+    async fn local_storage(&self) -> BrowserResult<BTreeMap<String, String>> {
+        let entries = self
+            .execute_script(
+                "const entries = {}; \
+                 for (let i = 0; i < localStorage.length; i++) { \
+                     const key = localStorage.key(i); entries[key] = localStorage.getItem(key); \
+                 } \
+                 return entries;",
+                vec![],
+            )
+            .await?;
+        Ok(serde_json::from_value(entries)?)
+    }
+
+    /// Writes `entries` into the `localStorage` of the current page's origin.
+    async fn set_local_storage(&self, entries: &BTreeMap<String, String>) -> BrowserResult<()> {
+        self.execute_script(
+            "for (const [key, value] of Object.entries(arguments[0])) { \
+                 localStorage.setItem(key, value); \
+             }",
+            vec![serde_json::to_value(entries)?],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Every element matching `locator`, in document order.
+    async fn find_elements(&self, locator: &Locator) -> BrowserResult<Vec<ElementInfo>> {
+        let script = format!("{}{}", RESOLVE_LOCATOR_JS, FIND_ELEMENTS_JS);
+        let found = self
+            .execute_script(&script, vec![locator.to_json()])
+            .await?;
+        Ok(serde_json::from_value(found)?)
+    }
+
+    async fn count(&self, locator: &Locator) -> BrowserResult<usize> {
+        let script = format!(
+            "{}return resolveLocator(arguments[0]).length;",
+            RESOLVE_LOCATOR_JS
+        );
+        let count = self
+            .execute_script(&script, vec![locator.to_json()])
+            .await?;
+        Ok(count.as_u64().unwrap_or(0) as usize)
+    }
+
+    /// The visible text of the page body.
+    async fn page_text(&self) -> BrowserResult<String> {
+        let text = self
+            .execute_script(
+                "return document.body ? document.body.innerText : '';",
+                vec![],
+            )
+            .await?;
+        Ok(text.as_str().unwrap_or_default().to_string())
+    }
+}
+
+/// Which [`BrowserBackend`] a run uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// A WebDriver server such as chromedriver or Selenium, see `webdriver_url`.
+    #[default]
+    WebDriver,
+    /// Playwright: attaches to `cdp_url` if set, otherwise launches its own Chromium.
+    Playwright,
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "webdriver" | "thirtyfour" | "selenium" => Ok(BackendKind::WebDriver),
+            "playwright" | "cdp" => Ok(BackendKind::Playwright),
+            other => Err(format!("Unknown browser backend: {}", other)),
+        }
+    }
+}
+
+/// Where and how to start browser sessions.
+#[derive(Debug, Clone)]
+pub struct BrowserOptions {
+    pub backend: BackendKind,
+    pub webdriver_url: String,
+    /// Chrome DevTools endpoint for [`BackendKind::Playwright`], e.g.
+    /// `http://localhost:9222`.
+    pub cdp_url: Option<String>,
+    pub headless: bool,
+    pub capabilities: BrowserCapabilities,
+}
+
+impl BrowserOptions {
+    pub fn new(webdriver_url: impl Into<String>) -> Self {
+        BrowserOptions {
+            backend: BackendKind::default(),
+            webdriver_url: webdriver_url.into(),
+            cdp_url: None,
+            headless: false,
+            capabilities: BrowserCapabilities::default(),
+        }
+    }
+
+    /// Starts a new browser session.
+    pub async fn launch(&self) -> BrowserResult<Arc<dyn BrowserBackend>> {
+        let capabilities = &self.capabilities;
+        Ok(match self.backend {
+            BackendKind::WebDriver => Arc::new(
+                WebDriverBackend::connect(&self.webdriver_url, self.headless, capabilities).await?,
+            ),
+            BackendKind::Playwright => Arc::new(match &self.cdp_url {
+                Some(url) => PlaywrightBackend::connect_over_cdp(url, capabilities).await?,
+                None => PlaywrightBackend::launch(self.headless, capabilities).await?,
+            }),
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// Which browser is started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowserKind {
+    #[default]
+    Chrome,
+    Firefox,
+}
+
+impl std::str::FromStr for BrowserKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "chrome" | "chromium" => Ok(BrowserKind::Chrome),
+            "firefox" | "gecko" => Ok(BrowserKind::Firefox),
+            other => Err(format!("Unknown browser: {}", other)),
+        }
+    }
+}
+
+/// Browser window (and viewport) size in CSS pixels, written `1280x800`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for WindowSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .trim()
+            .split_once(['x', 'X', ','])
+            .ok_or_else(|| format!("Invalid window size {:?}, expected WIDTHxHEIGHT", s))?;
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("Invalid window size {:?}: {}", s, e))
+        };
+        Ok(WindowSize {
+            width: parse(width)?,
+            height: parse(height)?,
+        })
+    }
+}
+
+/// How browser sessions are set up when they are created, so pages render the same way
+/// on every machine. Read from the `[browser]` table of the config file:
+///
+/// ```toml
+/// [browser]
+/// browser = "chrome"
+/// window_size = { width = 1280, height = 800 }
+/// user_agent = "Mozilla/5.0 ..."
+/// proxy = "http://proxy.internal:3128"
+/// locale = "en-US"
+/// timezone = "Europe/Berlin"
+/// args = ["--disable-gpu"]
+/// ```
+///
+/// Headless mode is the top-level `headless` setting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserCapabilities {
+    pub browser: BrowserKind,
+    pub window_size: Option<WindowSize>,
+    pub user_agent: Option<String>,
+    /// Proxy for all traffic, e.g. `http://host:3128` or `socks5://host:1080`.
+    pub proxy: Option<String>,
+    /// Language for `navigator.language` and `Accept-Language`, e.g. `en-US`.
+    pub locale: Option<String>,
+    /// IANA timezone, e.g. `Europe/Berlin`.
+    pub timezone: Option<String>,
+    /// Extra command-line arguments for the browser.
+    pub args: Vec<String>,
+}
+
+impl BrowserCapabilities {
+    /// Whether any setting applies to the browser context (as opposed to the launch), i.e.
+    /// whether an attached browser needs a fresh context to honour them.
+    pub fn has_context_settings(&self) -> bool {
+        self.window_size.is_some()
+            || self.user_agent.is_some()
+            || self.locale.is_some()
+            || self.timezone.is_some()
+    }
+}
+
+/// Splits `socks5://host:1080` into `(Some("socks5"), "host:1080")`.
+pub(crate) fn split_proxy(proxy: &str) -> (Option<&str>, &str) {
+    match proxy.split_once("://") {
+        Some((scheme, address)) => (Some(scheme), address.trim_end_matches('/')),
+        None => (None, proxy),
+    }
+}
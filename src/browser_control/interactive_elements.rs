@@ -1,87 +1,373 @@
-use std::collections::HashMap;
-use thirtyfour::By;
-use thirtyfour::prelude::*; // Ensure By is in scope
-
-// Helper function to generate XPath via JavaScript (from previous examples)
-// This function is assumed to be robust and is a good fallback.
-async fn generate_xpath_via_js(
-    driver: &WebDriver,
-    element: &WebElement,
-) -> WebDriverResult<Option<String>> {
-    let script = r#"
-        function getElementXPath(element) {
-            if (element && element.id) { return '//*[@id="' + element.id + '"]'; } // Prioritize ID
-            const paths = [];
-            for (; element && element.nodeType == Node.ELEMENT_NODE; element = element.parentNode) {
-                let index = 0;
-                for (let sibling = element.previousSibling; sibling; sibling = sibling.previousSibling) {
-                    if (sibling.nodeType == Node.DOCUMENT_TYPE_NODE) continue;
-                    if (sibling.nodeName == element.nodeName) ++index;
-                }
-                const tagName = element.nodeName.toLowerCase();
-                const pathIndex = (index ? "[" + (index + 1) + "]" : ""); // Sibling index
-                paths.splice(0, 0, tagName + pathIndex);
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, Locator};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use tracing::{debug, warn};
+
+/// An element the agent can act on, numbered for set-of-mark prompting: the same `id` is
+/// drawn on the annotated screenshot, shown in the element list of the prompt and accepted
+/// by actions such as `click_element`.
+///
+/// Its [`Display`](fmt::Display) form is the compact line used in prompts, e.g.
+/// `[3] textbox "Email" type=email placeholder="you@example.com" value=""`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InteractiveElement {
+    /// 1-based position in document order.
+    pub id: usize,
+    /// Descriptive name built from the tag and text, e.g. `button_sign_in`.
+    pub key: String,
+    pub tag: String,
+    /// ARIA role, explicit or implied by the tag.
+    pub role: Option<String>,
+    /// Accessible name, see [`DomElement::name`].
+    pub name: Option<String>,
+    /// Visible text; empty for form controls.
+    pub text: String,
+    pub placeholder: Option<String>,
+    /// Current value of form controls.
+    pub value: Option<String>,
+    pub href: Option<String>,
+    /// `type` attribute of `<input>` elements.
+    pub input_type: Option<String>,
+    pub enabled: bool,
+    /// Checked, pressed or selected state, for elements that have one.
+    pub checked: Option<bool>,
+    pub bounds: BoundingBox,
+    pub locator: Locator,
+}
+
+/// Longest text, name or value shown in a prompt line.
+const MAX_PROMPT_CHARS: usize = 80;
+
+/// Input types implied by the role, which the prompt line leaves out.
+const PLAIN_INPUT_TYPES: &[&str] = &["text", "checkbox", "radio", "button", "submit", "reset"];
+
+impl InteractiveElement {
+    fn from_dom(id: usize, key: String, element: &DomElement) -> Self {
+        let attribute = |name: &str| {
+            element
+                .attribute(name)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        InteractiveElement {
+            id,
+            key,
+            tag: element.tag.to_lowercase(),
+            role: element.role.clone(),
+            name: element.name.clone(),
+            text: element.text.clone(),
+            placeholder: attribute("placeholder"),
+            value: element.value.clone(),
+            href: attribute("href"),
+            input_type: (element.tag.eq_ignore_ascii_case("input")).then(|| {
+                attribute("type")
+                    .unwrap_or_else(|| "text".to_string())
+                    .to_lowercase()
+            }),
+            enabled: element.enabled,
+            checked: element.checked,
+            bounds: element.bounds,
+            locator: Locator::Css(element.selector.clone()),
+        }
+    }
+}
+
+/// `value` quoted, cut to [`MAX_PROMPT_CHARS`].
+fn quoted(value: &str) -> String {
+    match value.char_indices().nth(MAX_PROMPT_CHARS) {
+        Some((end, _)) => format!("{:?}", format!("{}…", &value[..end])),
+        None => format!("{:?}", value),
+    }
+}
+
+impl fmt::Display for InteractiveElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}",
+            self.id,
+            self.role.as_deref().unwrap_or(&self.tag)
+        )?;
+        if let Some(name) = &self.name {
+            write!(f, " {}", quoted(name))?;
+        }
+        if !self.text.is_empty() && self.name.as_deref() != Some(self.text.as_str()) {
+            write!(f, " text={}", quoted(&self.text))?;
+        }
+        if let Some(input_type) = &self.input_type
+            && !PLAIN_INPUT_TYPES.contains(&input_type.as_str())
+        {
+            write!(f, " type={}", input_type)?;
+        }
+        if let Some(placeholder) = &self.placeholder
+            && self.name.as_ref() != Some(placeholder)
+        {
+            write!(f, " placeholder={}", quoted(placeholder))?;
+        }
+        if let Some(value) = &self.value {
+            if self.input_type.as_deref() == Some("password") && !value.is_empty() {
+                write!(f, " value=\"***\"")?;
+            } else if !matches!(
+                self.role.as_deref(),
+                Some("button" | "checkbox" | "radio" | "switch")
+            ) {
+                write!(f, " value={}", quoted(value))?;
             }
-            return paths.length ? "/" + paths.join("/") : null;
         }
-        return getElementXPath(arguments[0]);
-    "#;
+        if let Some(href) = &self.href {
+            write!(f, " href={}", quoted(href))?;
+        }
+        match self.checked {
+            Some(true) => write!(f, " checked")?,
+            Some(false) => write!(f, " unchecked")?,
+            None => {}
+        }
+        if !self.enabled {
+            write!(f, " disabled")?;
+        }
+        Ok(())
+    }
+}
+
+/// The element with set-of-mark id `id`.
+pub fn find_by_id(elements: &[InteractiveElement], id: usize) -> Option<&InteractiveElement> {
+    elements.iter().find(|element| element.id == id)
+}
+
+/// Candidates for [`collect_dom_elements`]: links, buttons, form controls, ARIA widgets and
+/// anything focusable or editable.
+const CANDIDATE_SELECTOR: &str = "a[href], button, input:not([type='hidden']), select, textarea, \
+    [role='button'], [role='link'], [role='menuitem'], [role='menuitemcheckbox'], \
+    [role='menuitemradio'], [role='tab'], [role='option'], [role='treeitem'], \
+    [role='checkbox'], [role='radio'], [role='switch'], [role='combobox'], [role='textbox'], \
+    details > summary, [contenteditable='true'], [tabindex]:not([tabindex='-1'])";
+
+/// Collects every candidate in one pass. The selector of each element is checked to match
+/// only that element before it is returned: a unique id, test id, `name` or `aria-label`
+/// if there is one, otherwise an `nth-of-type` path up to the closest ancestor with a
+/// unique id.
+const COLLECT_DOM_ELEMENTS_JS: &str = r#"
+    const MAX_TEXT = 200;
+    const clip = (value) => value.length > MAX_TEXT ? value.slice(0, MAX_TEXT) : value;
+    const isUnique = (selector, element) => {
+        try {
+            const found = document.querySelectorAll(selector);
+            return found.length === 1 && found[0] === element;
+        } catch (e) {
+            return false;
+        }
+    };
+    const attributeSelector = (tag, name, value) =>
+        tag + '[' + name + '="' + CSS.escape(value) + '"]';
+    function selectorOf(element) {
+        const tag = element.tagName.toLowerCase();
+        if (element.id && isUnique('#' + CSS.escape(element.id), element)) {
+            return '#' + CSS.escape(element.id);
+        }
+        for (const name of ['data-testid', 'data-test', 'data-qa', 'name', 'aria-label']) {
+            const value = element.getAttribute(name);
+            if (value && isUnique(attributeSelector(tag, name, value), element)) {
+                return attributeSelector(tag, name, value);
+            }
+        }
+        const parts = [];
+        for (let node = element; node && node.nodeType === Node.ELEMENT_NODE; node = node.parentElement) {
+            if (node !== element && node.id && isUnique('#' + CSS.escape(node.id), node)) {
+                parts.unshift('#' + CSS.escape(node.id));
+                break;
+            }
+            const nodeTag = node.tagName.toLowerCase();
+            let index = 1;
+            for (let sibling = node.previousElementSibling; sibling; sibling = sibling.previousElementSibling) {
+                if (sibling.tagName === node.tagName) index++;
+            }
+            parts.unshift(nodeTag + ':nth-of-type(' + index + ')');
+            if (isUnique(parts.join(' > '), element)) break;
+        }
+        return parts.join(' > ');
+    }
+    const IMPLICIT_ROLES = {
+        a: 'link', button: 'button', select: 'combobox', textarea: 'textbox', summary: 'button',
+    };
+    const INPUT_ROLES = {
+        button: 'button', submit: 'button', reset: 'button', image: 'button',
+        checkbox: 'checkbox', radio: 'radio', range: 'slider', search: 'searchbox',
+    };
+    function roleOf(element, tag) {
+        const explicit = element.getAttribute('role');
+        if (explicit) return explicit.split(' ')[0];
+        if (tag === 'input') return INPUT_ROLES[(element.getAttribute('type') || 'text').toLowerCase()] || 'textbox';
+        if (tag === 'a' && !element.hasAttribute('href')) return null;
+        return IMPLICIT_ROLES[tag] || null;
+    }
+    const FORM_CONTROLS = ['input', 'select', 'textarea'];
+    const squash = (value) => (value || '').trim().replace(/\s+/g, ' ');
+    function visibleText(element, tag) {
+        // The text of form controls is their value (or every option of a select)
+        if (FORM_CONTROLS.includes(tag)) return '';
+        return squash(element.innerText || element.textContent);
+    }
+    function accessibleName(element, tag, text) {
+        const labelledBy = element.getAttribute('aria-labelledby');
+        if (labelledBy) {
+            const name = squash(labelledBy.split(/\s+/)
+                .map(id => document.getElementById(id))
+                .filter(Boolean)
+                .map(node => node.innerText || node.textContent)
+                .join(' '));
+            if (name) return name;
+        }
+        const label = squash(element.getAttribute('aria-label'));
+        if (label) return label;
+        if (element.labels && element.labels.length) {
+            const name = squash(Array.from(element.labels).map(l => l.innerText || l.textContent).join(' '));
+            if (name) return name;
+        }
+        const type = (element.getAttribute('type') || '').toLowerCase();
+        if (tag === 'input' && ['button', 'submit', 'reset'].includes(type)) {
+            return squash(element.value) || null;
+        }
+        if (text) return text;
+        const image = element.querySelector('img[alt]');
+        for (const candidate of [element.getAttribute('alt'), image && image.getAttribute('alt'),
+                                 element.getAttribute('title'), element.getAttribute('placeholder')]) {
+            if (squash(candidate)) return squash(candidate);
+        }
+        return null;
+    }
+    function valueOf(element, tag) {
+        if (tag === 'select') {
+            return Array.from(element.selectedOptions || []).map(o => squash(o.text)).join(', ');
+        }
+        if (FORM_CONTROLS.includes(tag)) return element.value;
+        if (element.isContentEditable) return squash(element.innerText);
+        return null;
+    }
+    function checkedOf(element, tag) {
+        const type = (element.getAttribute('type') || '').toLowerCase();
+        if (tag === 'input' && (type === 'checkbox' || type === 'radio')) return element.checked;
+        const aria = element.getAttribute('aria-checked') || element.getAttribute('aria-pressed')
+            || element.getAttribute('aria-selected');
+        return aria === null ? null : aria === 'true';
+    }
+    function isEnabled(element) {
+        return !element.disabled && !element.closest('fieldset:disabled')
+            && element.getAttribute('aria-disabled') !== 'true';
+    }
+    function isVisible(element, rect) {
+        const style = window.getComputedStyle(element);
+        if (style.visibility === 'hidden' || style.display === 'none' || style.opacity === '0') return false;
+        return rect.width > 0 && rect.height > 0;
+    }
+    return Array.from(document.querySelectorAll(arguments[0])).map(element => {
+        const tag = element.tagName.toLowerCase();
+        const rect = element.getBoundingClientRect();
+        const attributes = {};
+        for (const attribute of element.attributes) {
+            if (attribute.name !== 'style') attributes[attribute.name] = clip(attribute.value);
+        }
+        const text = visibleText(element, tag);
+        const name = accessibleName(element, tag, text);
+        const value = valueOf(element, tag);
+        return {
+            tag: tag,
+            text: clip(text),
+            role: roleOf(element, tag),
+            name: name === null ? null : clip(name),
+            value: value === null || value === undefined ? null : clip(String(value)),
+            enabled: isEnabled(element),
+            checked: checkedOf(element, tag),
+            attributes: attributes,
+            bounds: { x: rect.x, y: rect.y, width: rect.width, height: rect.height },
+            visible: isVisible(element, rect),
+            selector: selectorOf(element),
+        };
+    });
+"#;
 
-    // Convert WebElement to a Value that can be passed to JavaScript
-    let args = vec![element.to_json()?];
-    let result_json = driver.execute(script, args).await?;
+/// Position and size of an element in CSS pixels, relative to the viewport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// What [`collect_dom_elements`] reports about each candidate element.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DomElement {
+    pub tag: String,
+    /// Visible text with whitespace collapsed, at most 200 characters. Empty for form
+    /// controls.
+    pub text: String,
+    /// Explicit ARIA role, or the implicit one of the tag (`link`, `button`, `textbox`, ...).
+    pub role: Option<String>,
+    /// Accessible name: `aria-labelledby`, `aria-label`, `<label>`, text, then `alt`,
+    /// `title` or `placeholder`.
+    pub name: Option<String>,
+    /// Current value of form controls (the selected options' text for a `<select>`).
+    pub value: Option<String>,
+    pub enabled: bool,
+    /// Checked, pressed or selected state, for elements that have one.
+    pub checked: Option<bool>,
+    /// Every attribute except `style`, values cut to 200 characters.
+    pub attributes: BTreeMap<String, String>,
+    pub bounds: BoundingBox,
+    pub visible: bool,
+    /// CSS selector matching only this element.
+    pub selector: String,
+}
 
-    // Handle ScriptRet return type
-    match result_json.json() {
-        serde_json::Value::String(s) => Ok(Some(s.clone())),
-        serde_json::Value::Null => Ok(None),
-        _ => Ok(None), // Fallback for unexpected return types
+impl DomElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
     }
 }
 
-/// Finds all potentially clickable elements on the page and returns a HashMap
-/// where keys are descriptive strings and values are `By` locators.
-///
-/// The function prioritizes locators in this order:
-/// 1. `By::Id` if a unique ID attribute is present and verified to be unique.
-/// 2. `By::Css` using `tag_name[name='value']` if `name` attribute is present and leads to a unique element.
-/// 3. `By::XPath` generated via JavaScript if other strategies fail or are not unique.
-/// 4. An EXTREMELY UNRELIABLE basic `By::Css` selector as an absolute last resort.
-pub async fn get_all_clickable_element_locators(
-    driver: &WebDriver,
-) -> WebDriverResult<HashMap<String, By>> {
-    let mut clickable_elements_map = HashMap::new();
+/// Collects every interactive candidate on the page, in document order, with a single
+/// script call.
+pub async fn collect_dom_elements(browser: &dyn BrowserBackend) -> BrowserResult<Vec<DomElement>> {
+    let found = browser
+        .execute_script(COLLECT_DOM_ELEMENTS_JS, vec![json!(CANDIDATE_SELECTOR)])
+        .await?;
+    Ok(serde_json::from_value(found)?)
+}
 
-    let selector = "a[href], button, input[type='button'], input[type='submit'], \
-                    input[type='reset'], input[type='image'], input[type='checkbox'], \
-                    input[type='radio'], select, textarea, [role='button'], [role='link'], \
-                    [role='menuitem'], [role='menuitemcheckbox'], [role='menuitemradio'], \
-                    [role='tab'], [role='option'], [role='treeitem'], details > summary, \
-                    [contenteditable='true'], [tabindex]:not([tabindex='-1'])";
+/// Finds all potentially clickable elements on the page, in document order, and numbers
+/// them from 1. Each gets a descriptive key and a CSS [`Locator`] computed in the page,
+/// so the whole scan is one script call however large the page is.
+pub async fn get_all_clickable_element_locators(
+    browser: &dyn BrowserBackend,
+) -> BrowserResult<Vec<InteractiveElement>> {
+    let mut clickable_elements = Vec::new();
+    let mut keys = HashSet::new();
 
-    let elements: Vec<WebElement> = driver.find_all(By::Css(selector)).await?;
+    let elements = collect_dom_elements(browser).await?;
 
     for (index, element) in elements.iter().enumerate() {
-        if !element.is_displayed().await.unwrap_or(false) {
+        if !element.visible {
             continue;
         }
 
-        let tag_name = match element.tag_name().await {
-            Ok(tn) => tn.to_lowercase(),
-            Err(_) => {
-                eprintln!(
-                    "Warning: Could not get tag name for element (overall index {}). Skipping.",
-                    index
-                );
-                continue;
-            }
-        };
-
-        let text_content = element.text().await.unwrap_or_default().trim().to_string();
+        let tag_name = element.tag.to_lowercase();
+        let text_content = element.text.trim().to_string();
 
-        // Use text content for key generation since accessible_name is not available in thirtyfour
-        let descriptive_text_for_key = if !text_content.is_empty() {
-            text_content
+        // Name the element after its accessible name, or its `name` attribute if it has none
+        let label = element
+            .name
+            .as_deref()
+            .or(Some(text_content.as_str()))
+            .into_iter()
+            .chain(element.attribute("name"))
+            .map(str::trim)
+            .find(|label| !label.is_empty())
+            .unwrap_or_default();
+        let descriptive_text_for_key = if !label.is_empty() {
+            label
                 .split_whitespace()
                 .take(3)
                 .collect::<Vec<&str>>()
@@ -114,97 +400,35 @@ pub async fn get_all_clickable_element_locators(
         // Ensure final key uniqueness in the map
         let original_map_key_for_suffixing = map_key.clone();
         let mut key_suffix = 0;
-        while clickable_elements_map.contains_key(&map_key) {
+        while keys.contains(&map_key) {
             key_suffix += 1;
             map_key = format!("{}_{}", original_map_key_for_suffixing, key_suffix);
         }
         // 'map_key' is now guaranteed to be unique for insertion.
 
-        // --- Determine the best locator ---
-        let mut best_locator: Option<By> = None;
-
-        // 1. Try ID first, and verify it's unique on the page
-        if let Some(id_val) = element.attr("id").await? {
-            if !id_val.is_empty() {
-                // Lightweight check for ID uniqueness
-                if driver.find_all(By::Id(&id_val)).await?.len() == 1 {
-                    best_locator = Some(By::Id(id_val.clone()));
-                } else {
-                    eprintln!(
-                        "  Key: '{}' -> Warning: ID '{}' exists but is not unique. Trying other locators.",
-                        map_key, id_val
-                    );
-                }
-            }
-        }
-
-        // 2. If no unique ID, try CSS selector by 'name' attribute (common for form elements)
-        if best_locator.is_none() {
-            if let Some(name_val) = element.attr("name").await? {
-                if !name_val.is_empty() {
-                    // Escape single quotes for CSS selector: name='value_with_'_apostrophe'
-                    let safe_name_val = name_val.replace('\'', "\\'");
-                    let css_by_name = format!("{}[name='{}']", tag_name, safe_name_val);
-                    // Lightweight check for uniqueness
-                    if driver.find_all(By::Css(&css_by_name)).await?.len() == 1 {
-                        best_locator = Some(By::Css(css_by_name.clone()));
-                    } else {
-                        eprintln!(
-                            "  Key: '{}' -> Warning: CSS by name attribute '{}' is not unique. Trying XPath.",
-                            map_key, css_by_name
-                        );
-                    }
-                }
-            }
-        }
-
-        // 3. If other specific strategies fail or aren't unique, try generating XPath via JavaScript
-        if best_locator.is_none() {
-            if let Some(xpath_str) = generate_xpath_via_js(driver, element).await? {
-                // Basic check: does the generated XPath find at least one element?
-                if !driver.find_all(By::XPath(&xpath_str)).await?.is_empty() {
-                    best_locator = Some(By::XPath(xpath_str.clone()));
-                } else {
-                    eprintln!(
-                        "  Key: '{}' -> Warning: JS-generated XPath '{}' found no elements. Skipping this XPath.",
-                        map_key, xpath_str
-                    );
-                }
-            }
-        }
-
-        // 4. EXTREME FALLBACK: Basic CSS using tag name and the overall index.
-        //    This is HIGHLY UNRELIABLE and should generally be avoided or used with extreme caution.
-        if best_locator.is_none() {
-            let css_very_unreliable_fallback =
-                format!("html body {}:nth-of-type({})", tag_name, index + 1);
-
-            eprintln!(
-                "  Key: '{}' -> EXTREME FALLBACK (HIGHLY UNRELIABLE) CSS: By::Css('{}'). This locator is very likely to be incorrect or unstable. Consider manually inspecting this element and providing a better locator strategy if possible.",
-                map_key, css_very_unreliable_fallback
-            );
-            best_locator = Some(By::Css(css_very_unreliable_fallback));
-        }
-
-        if let Some(locator) = best_locator {
-            clickable_elements_map.insert(map_key, locator);
-        } else {
-            // This case should ideally not be hit if the extreme fallback is always generating *something*.
-            // However, if generate_xpath_via_js returned None and we decided not to have an extreme fallback, this is important.
-            eprintln!(
-                "FATAL WARNING: Could not determine ANY locator for element (tag: '{}', overall_index: {}). This element will NOT be added to the map.",
-                tag_name, index
+        if element.selector.is_empty() {
+            warn!(
+                "Key '{}': no selector for element (tag: '{}', index: {}). Skipping it.",
+                map_key, tag_name, index
             );
+            continue;
         }
+        debug!("Key '{}': selector '{}'", map_key, element.selector);
+        keys.insert(map_key.clone());
+        clickable_elements.push(InteractiveElement::from_dom(
+            clickable_elements.len() + 1,
+            map_key,
+            element,
+        ));
     }
 
-    Ok(clickable_elements_map)
+    Ok(clickable_elements)
 }
 
 /// Alias function for compatibility with existing code
 /// Returns the same result as get_all_clickable_element_locators
-pub async fn get_interactive_elements_in_hashmap(
-    driver: &WebDriver,
-) -> WebDriverResult<HashMap<String, By>> {
-    get_all_clickable_element_locators(driver).await
+pub async fn get_interactive_elements(
+    browser: &dyn BrowserBackend,
+) -> BrowserResult<Vec<InteractiveElement>> {
+    get_all_clickable_element_locators(browser).await
 }
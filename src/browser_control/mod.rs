@@ -1,3 +1,9 @@
+pub mod accessibility;
 pub mod actions;
-pub mod browser_use;
+pub mod backend;
+pub mod capabilities;
 pub mod interactive_elements;
+pub mod playwright_backend;
+pub mod profile;
+pub mod session_manager;
+pub mod webdriver_backend;
@@ -0,0 +1,254 @@
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, Locator, StoredCookie};
+use crate::browser_control::capabilities::{BrowserCapabilities, BrowserKind};
+use async_trait::async_trait;
+use playwright::Playwright;
+use playwright::api::{Browser, BrowserContext, Cookie, Page, ProxySettings, SameSite, Viewport};
+use serde_json::Value;
+use std::sync::Mutex;
+
+/// [`BrowserBackend`] over a browser launched by Playwright (Chromium or Firefox), or a
+/// Chromium attached through the Chrome DevTools Protocol.
+pub struct PlaywrightBackend {
+    // Owns the Playwright driver process; `browser` and `context` only hold weak references.
+    _playwright: Playwright,
+    browser: Browser,
+    context: BrowserContext,
+    page: Mutex<Page>,
+}
+
+impl PlaywrightBackend {
+    /// Launches a new browser.
+    pub async fn launch(headless: bool, capabilities: &BrowserCapabilities) -> BrowserResult<Self> {
+        let playwright = Playwright::initialize()
+            .await
+            .map_err(|e| format!("Failed to start Playwright: {}", e))?;
+        let browser_type = match capabilities.browser {
+            BrowserKind::Chrome => playwright.chromium(),
+            BrowserKind::Firefox => playwright.firefox(),
+        };
+        let mut launcher = browser_type
+            .launcher()
+            .headless(headless)
+            .args(&capabilities.args);
+        if let Some(proxy) = &capabilities.proxy {
+            launcher = launcher.proxy(ProxySettings {
+                server: proxy.clone(),
+                bypass: None,
+                username: None,
+                password: None,
+            });
+        }
+        let browser = launcher
+            .launch()
+            .await
+            .map_err(|e| format!("Failed to launch {:?}: {}", capabilities.browser, e))?;
+        let context = new_context(&browser, capabilities).await?;
+        Self::attach(playwright, browser, context).await
+    }
+
+    /// Attaches to a running Chromium, e.g. one started with
+    /// `--remote-debugging-port=9222` and `endpoint` `http://localhost:9222`.
+    ///
+    /// The browser's own context is used unless `capabilities` has viewport, user agent,
+    /// locale or timezone settings, which need a new context. Launch settings such as the
+    /// proxy and arguments are up to whoever started the browser.
+    pub async fn connect_over_cdp(
+        endpoint: &str,
+        capabilities: &BrowserCapabilities,
+    ) -> BrowserResult<Self> {
+        if capabilities.browser != BrowserKind::Chrome {
+            return Err("Only Chromium can be attached over CDP".into());
+        }
+        let playwright = Playwright::initialize()
+            .await
+            .map_err(|e| format!("Failed to start Playwright: {}", e))?;
+        let browser = playwright
+            .chromium()
+            .connect_over_cdp_builder(endpoint)
+            .connect_over_cdp()
+            .await
+            .map_err(|e| format!("Failed to connect over CDP to {}: {}", endpoint, e))?;
+        let existing = browser.contexts()?.into_iter().next();
+        let context = match existing {
+            Some(context) if !capabilities.has_context_settings() => context,
+            _ => new_context(&browser, capabilities).await?,
+        };
+        Self::attach(playwright, browser, context).await
+    }
+
+    /// Uses the context's first page, opening one if it has none.
+    async fn attach(
+        playwright: Playwright,
+        browser: Browser,
+        context: BrowserContext,
+    ) -> BrowserResult<Self> {
+        let page = match context.pages()?.into_iter().next() {
+            Some(page) => page,
+            None => context.new_page().await?,
+        };
+        Ok(PlaywrightBackend {
+            _playwright: playwright,
+            browser,
+            context,
+            page: Mutex::new(page),
+        })
+    }
+
+    fn page(&self) -> Page {
+        self.page.lock().unwrap().clone()
+    }
+
+    fn set_page(&self, page: Page) {
+        *self.page.lock().unwrap() = page;
+    }
+}
+
+/// A context with the viewport, user agent, locale and timezone from `capabilities`.
+async fn new_context(
+    browser: &Browser,
+    capabilities: &BrowserCapabilities,
+) -> BrowserResult<BrowserContext> {
+    let mut builder = browser.context_builder();
+    if let Some(size) = capabilities.window_size {
+        builder = builder.viewport(Some(Viewport {
+            width: size.width as i32,
+            height: size.height as i32,
+        }));
+    }
+    if let Some(user_agent) = &capabilities.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(locale) = &capabilities.locale {
+        builder = builder.locale(locale);
+    }
+    if let Some(timezone) = &capabilities.timezone {
+        builder = builder.timezone_id(timezone);
+    }
+    Ok(builder.build().await?)
+}
+
+#[async_trait]
+impl BrowserBackend for PlaywrightBackend {
+    fn name(&self) -> &str {
+        "playwright"
+    }
+
+    async fn goto(&self, url: &str) -> BrowserResult<()> {
+        self.page().goto_builder(url).goto().await?;
+        Ok(())
+    }
+
+    async fn back(&self) -> BrowserResult<()> {
+        self.page().go_back_builder().go_back().await?;
+        Ok(())
+    }
+
+    async fn current_url(&self) -> BrowserResult<String> {
+        Ok(self.page().url()?)
+    }
+
+    async fn click(&self, locator: &Locator) -> BrowserResult<()> {
+        let selector = locator.to_selector();
+        self.page().click_builder(&selector).click().await?;
+        Ok(())
+    }
+
+    async fn type_text(&self, locator: &Locator, text: &str) -> BrowserResult<()> {
+        let selector = locator.to_selector();
+        self.page().type_builer(&selector, text).r#type().await?;
+        Ok(())
+    }
+
+    async fn execute_script(&self, script: &str, args: Vec<Value>) -> BrowserResult<Value> {
+        // Playwright evaluates expressions rather than function bodies, so wrap the body
+        // the way WebDriver does: `arguments` and `return` behave the same on both.
+        let expression = format!(
+            "(args) => {{ const result = (function() {{ {} }}).apply(null, args); \
+             return result === undefined ? null : result; }}",
+            script
+        );
+        Ok(self.page().evaluate(&expression, args).await?)
+    }
+
+    async fn screenshot_png(&self) -> BrowserResult<Vec<u8>> {
+        Ok(self.page().screenshot_builder().screenshot().await?)
+    }
+
+    async fn new_tab(&self) -> BrowserResult<()> {
+        let page = self.context.new_page().await?;
+        self.set_page(page);
+        Ok(())
+    }
+
+    async fn tab_count(&self) -> BrowserResult<usize> {
+        Ok(self.context.pages()?.len())
+    }
+
+    async fn switch_tab(&self, index: usize) -> BrowserResult<()> {
+        let page = self
+            .context
+            .pages()?
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| format!("No tab at index {}", index))?;
+        page.bring_to_front().await?;
+        self.set_page(page);
+        Ok(())
+    }
+
+    async fn close_tab(&self) -> BrowserResult<()> {
+        self.page().close(None).await?;
+        if let Some(page) = self.context.pages()?.pop() {
+            page.bring_to_front().await?;
+            self.set_page(page);
+        }
+        Ok(())
+    }
+
+    async fn quit(&self) -> BrowserResult<()> {
+        self.browser.close().await?;
+        Ok(())
+    }
+
+    async fn cookies(&self) -> BrowserResult<Vec<StoredCookie>> {
+        let cookies = self.context.cookies(&[]).await?;
+        Ok(cookies
+            .into_iter()
+            .map(|cookie| StoredCookie {
+                name: cookie.name,
+                value: cookie.value,
+                domain: cookie.domain,
+                path: cookie.path,
+                // Playwright reports session cookies as -1.
+                expires: cookie.expires.filter(|&e| e >= 0.0).map(|e| e as i64),
+                secure: cookie.secure.unwrap_or(false),
+                http_only: cookie.http_only.unwrap_or(false),
+                same_site: cookie.same_site.map(|same_site| format!("{:?}", same_site)),
+            })
+            .collect())
+    }
+
+    async fn add_cookies(&self, cookies: &[StoredCookie]) -> BrowserResult<()> {
+        let cookies: Vec<Cookie> = cookies
+            .iter()
+            .map(|stored| Cookie {
+                name: stored.name.clone(),
+                value: stored.value.clone(),
+                url: None,
+                domain: stored.domain.clone(),
+                path: Some(stored.path.clone().unwrap_or_else(|| "/".to_string())),
+                expires: stored.expires.map(|e| e as f64),
+                http_only: Some(stored.http_only),
+                secure: Some(stored.secure),
+                same_site: match stored.same_site.as_deref() {
+                    Some("Strict") => Some(SameSite::Strict),
+                    Some("Lax") => Some(SameSite::Lax),
+                    Some("None") => Some(SameSite::None),
+                    _ => None,
+                },
+            })
+            .collect();
+        self.context.add_cookies(&cookies).await?;
+        Ok(())
+    }
+}
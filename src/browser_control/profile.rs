@@ -0,0 +1,398 @@
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, StoredCookie};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2::{self, PBKDF2_HMAC_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+/// Environment variable holding the passphrase profiles are encrypted with. It is never
+/// read from the config file.
+pub const PROFILE_KEY_ENV: &str = "OCULUS_PROFILE_KEY";
+
+const PBKDF2_ITERATIONS: u32 = 210_000;
+const SALT_LEN: usize = 16;
+const AAD: &[u8] = b"oculus-browser-profile-v1";
+
+/// The `[profile]` table of the config file.
+///
+/// ```toml
+/// [profile]
+/// file = ".oculus/dashboard.profile"
+/// domains = ["dashboard.example.com"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Encrypted profile restored into new sessions. `None` disables profiles.
+    pub file: Option<PathBuf>,
+    /// Domains whose cookies and `localStorage` are kept, subdomains included. Empty means
+    /// the domain of the page each session ends on.
+    pub domains: Vec<String>,
+    /// Write the sessions' state back to the file when the run ends.
+    pub save: bool,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        ProfileConfig {
+            file: None,
+            domains: Vec::new(),
+            save: true,
+        }
+    }
+}
+
+/// Cookies and `localStorage` saved from browser sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileState {
+    pub saved_at: u64,
+    pub cookies: Vec<StoredCookie>,
+    /// `localStorage` entries by origin, e.g. `https://dashboard.example.com`.
+    pub local_storage: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ProfileState {
+    /// Adds `other`, replacing cookies with the same name, domain and path and storage
+    /// entries of the same origin.
+    pub fn merge(&mut self, other: ProfileState) {
+        for cookie in other.cookies {
+            self.cookies.retain(|existing| {
+                (&existing.name, &existing.domain, &existing.path)
+                    != (&cookie.name, &cookie.domain, &cookie.path)
+            });
+            self.cookies.push(cookie);
+        }
+        self.local_storage.extend(other.local_storage);
+    }
+
+    fn drop_expired(&mut self) {
+        let now = unix_secs() as i64;
+        self.cookies
+            .retain(|cookie| cookie.expires.is_none_or(|expires| expires > now));
+    }
+}
+
+/// The on-disk format: the serialized [`ProfileState`] encrypted with AES-256-GCM under a
+/// key derived from the passphrase with PBKDF2-HMAC-SHA256.
+#[derive(Serialize, Deserialize)]
+struct EncryptedProfile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// An encrypted profile file and the state loaded from it.
+///
+/// New sessions are seeded with [`BrowserProfile::restore`]; sessions about to close are
+/// read with [`BrowserProfile::capture`], and [`BrowserProfile::save`] writes the merged
+/// state back.
+pub struct BrowserProfile {
+    path: PathBuf,
+    passphrase: String,
+    domains: Vec<String>,
+    state: Mutex<ProfileState>,
+}
+
+impl BrowserProfile {
+    /// Opens `path` with the passphrase from [`PROFILE_KEY_ENV`]. A missing file starts an
+    /// empty profile.
+    pub fn open(path: &Path, domains: Vec<String>) -> BrowserResult<Self> {
+        let passphrase = std::env::var(PROFILE_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| format!("Set {} to use the browser profile", PROFILE_KEY_ENV))?;
+        Self::open_with_passphrase(path, passphrase, domains)
+    }
+
+    pub fn open_with_passphrase(
+        path: &Path,
+        passphrase: String,
+        domains: Vec<String>,
+    ) -> BrowserResult<Self> {
+        let state = if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read profile {}: {}", path.display(), e))?;
+            let mut state = decrypt(&contents, &passphrase)
+                .map_err(|e| format!("Failed to open profile {}: {}", path.display(), e))?;
+            state.drop_expired();
+            state
+        } else {
+            ProfileState::default()
+        };
+        Ok(BrowserProfile {
+            path: path.to_path_buf(),
+            passphrase,
+            domains: domains
+                .into_iter()
+                .map(|domain| domain.trim().trim_start_matches('.').to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect(),
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn state(&self) -> ProfileState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Adds `state` to the profile, see [`ProfileState::merge`].
+    pub fn merge(&self, state: ProfileState) {
+        self.state.lock().unwrap().merge(state);
+    }
+
+    fn is_kept(&self, host: &str) -> bool {
+        self.domains.is_empty() || self.domains.iter().any(|domain| host_matches(host, domain))
+    }
+
+    /// Loads the saved cookies and `localStorage` into `browser`, visiting each saved
+    /// origin to do so.
+    pub async fn restore(&self, browser: &dyn BrowserBackend) -> BrowserResult<()> {
+        let state = self.state();
+        let mut origins: BTreeMap<String, (Vec<StoredCookie>, BTreeMap<String, String>)> = state
+            .local_storage
+            .into_iter()
+            .map(|(origin, entries)| (origin, (Vec::new(), entries)))
+            .collect();
+        // Cookies are set while on a page of their domain, preferring an origin that is
+        // visited for its storage anyway.
+        for cookie in state.cookies {
+            let Some(host) = cookie.domain.as_deref().map(|d| d.trim_start_matches('.')) else {
+                continue;
+            };
+            let scheme = if cookie.secure { "https" } else { "http" };
+            let origin = origins
+                .keys()
+                .find(|origin| origin_host(origin).is_some_and(|h| host_matches(h, host)))
+                .cloned()
+                .unwrap_or_else(|| format!("{}://{}", scheme, host));
+            origins.entry(origin).or_default().0.push(cookie);
+        }
+
+        for (origin, (cookies, entries)) in &origins {
+            browser.goto(origin).await?;
+            if !cookies.is_empty() {
+                browser.add_cookies(cookies).await?;
+            }
+            if !entries.is_empty() {
+                browser.set_local_storage(entries).await?;
+            }
+            debug!(
+                "Restored {} cookie(s) and {} storage entries for {}",
+                cookies.len(),
+                entries.len(),
+                origin
+            );
+        }
+        if !origins.is_empty() {
+            info!(
+                "Restored browser profile {} for {} origin(s)",
+                self.path.display(),
+                origins.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads the cookies and `localStorage` of the profile's domains from `browser` into
+    /// the profile. With no domains configured, only the current page's domain is read;
+    /// otherwise each domain is visited, as WebDriver only exposes the current page's
+    /// cookies.
+    pub async fn capture(&self, browser: &dyn BrowserBackend) -> BrowserResult<()> {
+        let mut captured = ProfileState::default();
+        if self.domains.is_empty() {
+            self.capture_current(browser, &mut captured).await?;
+        } else {
+            for domain in &self.domains {
+                browser.goto(&format!("https://{}/", domain)).await?;
+                self.capture_current(browser, &mut captured).await?;
+            }
+        }
+        debug!(
+            "Captured {} cookie(s) and storage for {} origin(s)",
+            captured.cookies.len(),
+            captured.local_storage.len()
+        );
+        self.merge(captured);
+        Ok(())
+    }
+
+    async fn capture_current(
+        &self,
+        browser: &dyn BrowserBackend,
+        captured: &mut ProfileState,
+    ) -> BrowserResult<()> {
+        let url = browser.current_url().await?;
+        let Some(host) = origin_host(&url) else {
+            return Ok(());
+        };
+        if !self.is_kept(host) {
+            return Ok(());
+        }
+        let origin = url.splitn(4, '/').take(3).collect::<Vec<_>>().join("/");
+        let cookies = browser.cookies().await?.into_iter().filter(|cookie| {
+            cookie
+                .domain
+                .as_deref()
+                .map(|d| d.trim_start_matches('.'))
+                .is_none_or(|d| self.is_kept(d))
+        });
+        captured.merge(ProfileState {
+            saved_at: 0,
+            cookies: cookies.collect(),
+            local_storage: BTreeMap::new(),
+        });
+        let entries = browser.local_storage().await?;
+        if !entries.is_empty() {
+            captured.local_storage.insert(origin, entries);
+        }
+        Ok(())
+    }
+
+    /// Encrypts the profile state and writes it to the profile file.
+    pub fn save(&self) -> BrowserResult<()> {
+        let mut state = self.state();
+        state.drop_expired();
+        state.saved_at = unix_secs();
+        let contents = encrypt(&state, &self.passphrase)?;
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        write_private(&tmp, contents.as_bytes())?;
+        std::fs::rename(&tmp, &self.path)?;
+        info!(
+            "Saved {} cookie(s) to browser profile {}",
+            state.cookies.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+/// `host` is `domain` or one of its subdomains. `domain` is expected in lower case.
+pub fn host_matches(host: &str, domain: &str) -> bool {
+    let host = host.to_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// `example.com` for `https://example.com:8443/path`.
+fn origin_host(url: &str) -> Option<&str> {
+    let (scheme, rest) = url.split_once("://")?;
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> BrowserResult<LessSafeKey> {
+    let iterations = NonZeroU32::new(iterations).ok_or("Invalid PBKDF2 iteration count")?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "Invalid profile key")?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn encrypt(state: &ProfileState, passphrase: &str) -> BrowserResult<String> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt).map_err(|_| "No randomness available")?;
+    rng.fill(&mut nonce)
+        .map_err(|_| "No randomness available")?;
+
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+    let mut data = serde_json::to_vec(state)?;
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(AAD),
+        &mut data,
+    )
+    .map_err(|_| "Failed to encrypt the profile")?;
+
+    Ok(serde_json::to_string_pretty(&EncryptedProfile {
+        version: 1,
+        iterations: PBKDF2_ITERATIONS,
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(data),
+    })?)
+}
+
+fn decrypt(contents: &str, passphrase: &str) -> BrowserResult<ProfileState> {
+    let file: EncryptedProfile = serde_json::from_str(contents)?;
+    if file.version != 1 {
+        return Err(format!("Unsupported profile version {}", file.version).into());
+    }
+    let salt = BASE64.decode(&file.salt)?;
+    let nonce: [u8; NONCE_LEN] = BASE64
+        .decode(&file.nonce)?
+        .try_into()
+        .map_err(|_| "Invalid nonce")?;
+    let mut data = BASE64.decode(&file.ciphertext)?;
+    // The count is stored for future format changes, but an edited file must not be able
+    // to stall key derivation or weaken the key.
+    if file.iterations != PBKDF2_ITERATIONS {
+        return Err(format!("Unsupported PBKDF2 iteration count {}", file.iterations).into());
+    }
+
+    let key = derive_key(passphrase, &salt, file.iterations)?;
+    let plaintext = key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(AAD),
+            &mut data,
+        )
+        .map_err(|_| "Wrong key or corrupted profile")?;
+    Ok(serde_json::from_slice(plaintext)?)
+}
+
+/// Writes `contents` readable by the current user only, where the platform allows it.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(contents)
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::write(path, contents)
+    }
+}
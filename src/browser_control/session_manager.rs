@@ -0,0 +1,224 @@
+use crate::browser_control::backend::{BrowserBackend, BrowserOptions, BrowserResult};
+use crate::browser_control::profile::BrowserProfile;
+use futures::future::join_all;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Launches, lends out and closes the browser sessions of a run.
+///
+/// A released session is kept idle and handed to a later step that depends on the step that
+/// released it, so logins and open pages carry over; unrelated steps get a fresh session
+/// rather than another step's tabs and cookies. Every session
+/// the manager started is tracked until it is closed, so [`BrowserSessionManager::shutdown`]
+/// also cleans up sessions whose step was cancelled mid-run.
+///
+/// With a [`BrowserProfile`], new sessions start with its cookies and `localStorage`, and
+/// each session's state is captured into it before the session is closed.
+pub struct BrowserSessionManager {
+    options: BrowserOptions,
+    reuse: bool,
+    max_idle: usize,
+    profile: Option<Arc<BrowserProfile>>,
+    save_profile: bool,
+    state: Mutex<Sessions>,
+}
+
+#[derive(Default)]
+struct Sessions {
+    /// Every session started and not yet closed, idle or in use.
+    live: Vec<Arc<dyn BrowserBackend>>,
+    idle: Vec<IdleSession>,
+    shut_down: bool,
+}
+
+struct IdleSession {
+    /// The step that last used the session.
+    owner: String,
+    browser: Arc<dyn BrowserBackend>,
+}
+
+impl BrowserSessionManager {
+    pub fn new(options: BrowserOptions) -> Self {
+        BrowserSessionManager {
+            options,
+            reuse: true,
+            max_idle: 1,
+            profile: None,
+            save_profile: true,
+            state: Mutex::new(Sessions::default()),
+        }
+    }
+
+    /// Whether released sessions are kept for later steps. Without reuse every step gets a
+    /// fresh browser that is closed when the step finishes.
+    pub fn with_reuse(mut self, reuse: bool) -> Self {
+        self.reuse = reuse;
+        self
+    }
+
+    /// How many idle sessions are kept; further released sessions are closed. Clamped to 1.
+    pub fn with_max_idle(mut self, max_idle: usize) -> Self {
+        self.max_idle = max_idle.max(1);
+        self
+    }
+
+    /// Restores `profile` into every new session. With `save`, the sessions' state is
+    /// captured before they close and written to the profile file on
+    /// [`BrowserSessionManager::shutdown`].
+    pub fn with_profile(mut self, profile: Arc<BrowserProfile>, save: bool) -> Self {
+        self.profile = Some(profile);
+        self.save_profile = save;
+        self
+    }
+
+    /// Number of sessions currently open, idle or in use.
+    pub fn live_sessions(&self) -> usize {
+        self.state.lock().unwrap().live.len()
+    }
+
+    /// Hands out the idle session last used by the first step in `preferred` that has one,
+    /// or starts a new session. Idle sessions that no longer respond are closed and
+    /// skipped.
+    pub async fn acquire(&self, preferred: &[String]) -> BrowserResult<Arc<dyn BrowserBackend>> {
+        while let Some(idle) = self.take_idle(preferred)? {
+            match idle.browser.current_url().await {
+                Ok(url) => {
+                    debug!(
+                        "Reusing the browser session of step {} at {}",
+                        idle.owner, url
+                    );
+                    return Ok(idle.browser);
+                }
+                Err(e) => {
+                    warn!(
+                        "Browser session of step {} no longer responds: {}. Closing it.",
+                        idle.owner, e
+                    );
+                    self.close(idle.browser).await;
+                }
+            }
+        }
+
+        let browser = self.options.launch().await?;
+        if let Some(profile) = &self.profile
+            && let Err(e) = profile.restore(browser.as_ref()).await
+        {
+            warn!(
+                "Failed to restore browser profile {}: {}",
+                profile.path().display(),
+                e
+            );
+        }
+        let open = {
+            let mut state = self.state.lock().unwrap();
+            (!state.shut_down).then(|| {
+                state.live.push(browser.clone());
+                state.live.len()
+            })
+        };
+        let Some(open) = open else {
+            // Started while shutting down: nothing else would close it.
+            let _ = browser.quit().await;
+            return Err("Browser sessions were shut down".into());
+        };
+        info!(
+            "Started a {} browser session ({} open)",
+            browser.name(),
+            open
+        );
+        Ok(browser)
+    }
+
+    fn take_idle(&self, preferred: &[String]) -> BrowserResult<Option<IdleSession>> {
+        let mut state = self.state.lock().unwrap();
+        if state.shut_down {
+            return Err("Browser sessions were shut down".into());
+        }
+        let position = preferred
+            .iter()
+            .find_map(|owner| state.idle.iter().position(|idle| &idle.owner == owner));
+        Ok(position.map(|position| state.idle.remove(position)))
+    }
+
+    /// Returns a session after `owner` is done with it. It is kept for reuse, closing the
+    /// longest idle session if enough are already idle, or closed if reuse is off.
+    pub async fn release(&self, owner: &str, browser: Arc<dyn BrowserBackend>) {
+        let evicted = {
+            let mut state = self.state.lock().unwrap();
+            if !state.live.iter().any(|live| Arc::ptr_eq(live, &browser)) {
+                // Already closed, e.g. by `shutdown`.
+                return;
+            }
+            if !self.reuse || state.shut_down {
+                browser
+            } else {
+                state.idle.push(IdleSession {
+                    owner: owner.to_string(),
+                    browser,
+                });
+                if state.idle.len() <= self.max_idle {
+                    return;
+                }
+                state.idle.remove(0).browser
+            }
+        };
+        self.close(evicted).await;
+    }
+
+    /// Quits `browser` and stops tracking it.
+    pub async fn close(&self, browser: Arc<dyn BrowserBackend>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.live.retain(|live| !Arc::ptr_eq(live, &browser));
+            state
+                .idle
+                .retain(|idle| !Arc::ptr_eq(&idle.browser, &browser));
+        }
+        self.capture_profile(browser.as_ref()).await;
+        if let Err(e) = browser.quit().await {
+            warn!("Failed to close browser session: {}", e);
+        }
+    }
+
+    /// Quits every open session, including ones still in use, and refuses to start new
+    /// ones. Safe to call more than once.
+    pub async fn shutdown(&self) {
+        let sessions = {
+            let mut state = self.state.lock().unwrap();
+            state.shut_down = true;
+            state.idle.clear();
+            std::mem::take(&mut state.live)
+        };
+        if sessions.is_empty() {
+            return;
+        }
+        info!("Closing {} browser session(s)", sessions.len());
+        for browser in &sessions {
+            self.capture_profile(browser.as_ref()).await;
+        }
+        for result in join_all(sessions.iter().map(|browser| browser.quit())).await {
+            if let Err(e) = result {
+                warn!("Failed to close browser session: {}", e);
+            }
+        }
+        if let Some(profile) = &self.profile
+            && self.save_profile
+            && let Err(e) = profile.save()
+        {
+            warn!(
+                "Failed to save browser profile {}: {}",
+                profile.path().display(),
+                e
+            );
+        }
+    }
+
+    async fn capture_profile(&self, browser: &dyn BrowserBackend) {
+        if let Some(profile) = &self.profile
+            && self.save_profile
+            && let Err(e) = profile.capture(browser).await
+        {
+            warn!("Failed to capture the browser profile: {}", e);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use crate::browser_control::backend::{BrowserBackend, BrowserResult, Locator, StoredCookie};
+use crate::browser_control::capabilities::{BrowserCapabilities, BrowserKind, split_proxy};
+use async_trait::async_trait;
+use reqwest::Method;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use thirtyfour::Proxy;
+use thirtyfour::common::capabilities::firefox::FirefoxPreferences;
+use thirtyfour::common::command::{Command, ExtensionCommand};
+use thirtyfour::extensions::cdp::ChromeDevTools;
+use thirtyfour::prelude::*;
+use tracing::warn;
+
+/// [`BrowserBackend`] over a WebDriver server (chromedriver, Selenium, ...).
+pub struct WebDriverBackend {
+    driver: WebDriver,
+    browser: BrowserKind,
+}
+
+impl WebDriverBackend {
+    /// Starts a session on the WebDriver server at `webdriver_url`.
+    pub async fn connect(
+        webdriver_url: &str,
+        headless: bool,
+        capabilities: &BrowserCapabilities,
+    ) -> BrowserResult<Self> {
+        let caps = desired_capabilities(headless, capabilities)
+            .map_err(|e| format!("Invalid browser capabilities: {}", e))?;
+        let driver = WebDriver::new(webdriver_url, caps)
+            .await
+            .map_err(|e| format!("Failed to start browser session: {}", e))?;
+
+        if let Some(timezone) = &capabilities.timezone {
+            match capabilities.browser {
+                BrowserKind::Chrome => {
+                    let result = ChromeDevTools::new(driver.handle.clone())
+                        .execute_cdp_with_params(
+                            "Emulation.setTimezoneOverride",
+                            json!({ "timezoneId": timezone }),
+                        )
+                        .await;
+                    if let Err(e) = result {
+                        let _ = driver.clone().quit().await;
+                        return Err(format!("Failed to set timezone {}: {}", timezone, e).into());
+                    }
+                }
+                BrowserKind::Firefox => {
+                    warn!(
+                        "Ignoring timezone {}: not supported for Firefox over WebDriver",
+                        timezone
+                    );
+                }
+            }
+        }
+        Ok(WebDriverBackend {
+            driver,
+            browser: capabilities.browser,
+        })
+    }
+
+    /// Wraps an existing session of `browser`.
+    pub fn from_driver(driver: WebDriver, browser: BrowserKind) -> Self {
+        WebDriverBackend { driver, browser }
+    }
+
+    fn devtools(&self) -> ChromeDevTools {
+        ChromeDevTools::new(self.driver.handle.clone())
+    }
+
+    pub fn driver(&self) -> &WebDriver {
+        &self.driver
+    }
+}
+
+fn desired_capabilities(
+    headless: bool,
+    capabilities: &BrowserCapabilities,
+) -> BrowserResult<Capabilities> {
+    match capabilities.browser {
+        BrowserKind::Chrome => {
+            let mut caps = DesiredCapabilities::chrome();
+            if headless {
+                caps.set_headless()?;
+            }
+            if let Some(size) = capabilities.window_size {
+                caps.add_arg(&format!("--window-size={},{}", size.width, size.height))?;
+            }
+            if let Some(user_agent) = &capabilities.user_agent {
+                caps.add_arg(&format!("--user-agent={}", user_agent))?;
+            }
+            if let Some(proxy) = &capabilities.proxy {
+                caps.add_arg(&format!("--proxy-server={}", proxy))?;
+            }
+            if let Some(locale) = &capabilities.locale {
+                caps.add_arg(&format!("--lang={}", locale))?;
+                caps.add_experimental_option("prefs", json!({ "intl.accept_languages": locale }))?;
+            }
+            for arg in &capabilities.args {
+                caps.add_arg(arg)?;
+            }
+            Ok(caps.into())
+        }
+        BrowserKind::Firefox => {
+            let mut caps = DesiredCapabilities::firefox();
+            if headless {
+                caps.set_headless()?;
+            }
+            if let Some(size) = capabilities.window_size {
+                caps.add_arg(&format!("--width={}", size.width))?;
+                caps.add_arg(&format!("--height={}", size.height))?;
+            }
+            let mut prefs = FirefoxPreferences::new();
+            if let Some(user_agent) = &capabilities.user_agent {
+                prefs.set_user_agent(user_agent.clone())?;
+            }
+            if let Some(locale) = &capabilities.locale {
+                prefs.set("intl.accept_languages", locale)?;
+                prefs.set("intl.locale.requested", locale)?;
+            }
+            caps.set_preferences(prefs)?;
+            if let Some(proxy) = &capabilities.proxy {
+                caps.set_proxy(firefox_proxy(proxy))?;
+            }
+            for arg in &capabilities.args {
+                caps.add_arg(arg)?;
+            }
+            Ok(caps.into())
+        }
+    }
+}
+
+/// Geckodriver takes the proxy as a capability rather than a command-line flag.
+fn firefox_proxy(proxy: &str) -> Proxy {
+    let (scheme, address) = split_proxy(proxy);
+    let address = Some(address.to_string());
+    match scheme {
+        Some(scheme) if scheme.starts_with("socks") => Proxy::Manual {
+            ftp_proxy: None,
+            http_proxy: None,
+            ssl_proxy: None,
+            socks_proxy: address,
+            socks_version: Some(if scheme == "socks4" { 4 } else { 5 }),
+            socks_username: None,
+            socks_password: None,
+            no_proxy: None,
+        },
+        _ => Proxy::Manual {
+            ftp_proxy: None,
+            http_proxy: address.clone(),
+            ssl_proxy: address,
+            socks_proxy: None,
+            socks_version: None,
+            socks_username: None,
+            socks_password: None,
+            no_proxy: None,
+        },
+    }
+}
+
+fn by(locator: &Locator) -> By {
+    match locator {
+        Locator::Css(css) => By::Css(css.clone()),
+        Locator::XPath(xpath) => By::XPath(xpath.clone()),
+        Locator::Id(id) => By::Id(id.clone()),
+    }
+}
+
+#[async_trait]
+impl BrowserBackend for WebDriverBackend {
+    fn name(&self) -> &str {
+        "webdriver"
+    }
+
+    async fn goto(&self, url: &str) -> BrowserResult<()> {
+        self.driver.goto(url).await?;
+        Ok(())
+    }
+
+    async fn back(&self) -> BrowserResult<()> {
+        self.driver.back().await?;
+        Ok(())
+    }
+
+    async fn current_url(&self) -> BrowserResult<String> {
+        Ok(self.driver.current_url().await?.to_string())
+    }
+
+    async fn click(&self, locator: &Locator) -> BrowserResult<()> {
+        self.driver.find(by(locator)).await?.click().await?;
+        Ok(())
+    }
+
+    async fn type_text(&self, locator: &Locator, text: &str) -> BrowserResult<()> {
+        self.driver.find(by(locator)).await?.send_keys(text).await?;
+        Ok(())
+    }
+
+    async fn execute_script(&self, script: &str, args: Vec<Value>) -> BrowserResult<Value> {
+        let ret = self.driver.execute(script, args).await?;
+        Ok(ret.json().clone())
+    }
+
+    async fn screenshot_png(&self) -> BrowserResult<Vec<u8>> {
+        Ok(self.driver.screenshot_as_png().await?)
+    }
+
+    async fn new_tab(&self) -> BrowserResult<()> {
+        let handle = self.driver.new_tab().await?;
+        self.driver.switch_to_window(handle).await?;
+        Ok(())
+    }
+
+    async fn tab_count(&self) -> BrowserResult<usize> {
+        Ok(self.driver.windows().await?.len())
+    }
+
+    async fn switch_tab(&self, index: usize) -> BrowserResult<()> {
+        let handle = self
+            .driver
+            .windows()
+            .await?
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| format!("No tab at index {}", index))?;
+        self.driver.switch_to_window(handle).await?;
+        Ok(())
+    }
+
+    async fn close_tab(&self) -> BrowserResult<()> {
+        self.driver.close_window().await?;
+        if let Some(handle) = self.driver.windows().await?.pop() {
+            self.driver.switch_to_window(handle).await?;
+        }
+        Ok(())
+    }
+
+    async fn quit(&self) -> BrowserResult<()> {
+        self.driver.clone().quit().await?;
+        Ok(())
+    }
+
+    /// Chrome's cookies are read over CDP, for every domain at once; other browsers return
+    /// the current page's cookies. Both keep the `HttpOnly` flag, which thirtyfour's
+    /// `Cookie` leaves out.
+    async fn cookies(&self) -> BrowserResult<Vec<StoredCookie>> {
+        let cookies = match self.browser {
+            BrowserKind::Chrome => {
+                let response = self.devtools().execute_cdp("Network.getAllCookies").await?;
+                response["cookies"].as_array().cloned().unwrap_or_default()
+            }
+            BrowserKind::Firefox => {
+                let response = self
+                    .driver
+                    .handle
+                    .cmd(Command::ExtensionCommand(Box::new(CookieCommand(None))))
+                    .await?
+                    .value_json()?;
+                response.as_array().cloned().unwrap_or_default()
+            }
+        };
+        Ok(cookies.iter().filter_map(stored_cookie).collect())
+    }
+
+    async fn add_cookies(&self, cookies: &[StoredCookie]) -> BrowserResult<()> {
+        match self.browser {
+            BrowserKind::Chrome => {
+                let cookies: Vec<Value> = cookies.iter().map(cdp_cookie).collect();
+                self.devtools()
+                    .execute_cdp_with_params("Network.setCookies", json!({ "cookies": cookies }))
+                    .await?;
+            }
+            BrowserKind::Firefox => {
+                for stored in cookies {
+                    self.driver
+                        .handle
+                        .cmd(Command::ExtensionCommand(Box::new(CookieCommand(Some(
+                            webdriver_cookie(stored),
+                        )))))
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The W3C cookie endpoint with raw JSON: `None` reads the current page's cookies,
+/// `Some(cookie)` adds one.
+#[derive(Debug)]
+struct CookieCommand(Option<Value>);
+
+impl ExtensionCommand for CookieCommand {
+    fn parameters_json(&self) -> Option<Value> {
+        self.0.as_ref().map(|cookie| json!({ "cookie": cookie }))
+    }
+
+    fn method(&self) -> Method {
+        match self.0 {
+            Some(_) => Method::POST,
+            None => Method::GET,
+        }
+    }
+
+    fn endpoint(&self) -> Arc<str> {
+        Arc::from("/cookie")
+    }
+}
+
+/// Reads a cookie in the CDP (`expires`, `-1` for session cookies) or the WebDriver
+/// (`expiry`) shape.
+fn stored_cookie(cookie: &Value) -> Option<StoredCookie> {
+    let expires = cookie["expiry"]
+        .as_f64()
+        .or_else(|| cookie["expires"].as_f64())
+        .filter(|expires| *expires > 0.0 && !cookie["session"].as_bool().unwrap_or(false));
+    Some(StoredCookie {
+        name: cookie["name"].as_str()?.to_string(),
+        value: cookie["value"].as_str()?.to_string(),
+        domain: cookie["domain"].as_str().map(str::to_string),
+        path: cookie["path"].as_str().map(str::to_string),
+        expires: expires.map(|expires| expires as i64),
+        secure: cookie["secure"].as_bool().unwrap_or(false),
+        http_only: cookie["httpOnly"].as_bool().unwrap_or(false),
+        same_site: cookie["sameSite"].as_str().map(str::to_string),
+    })
+}
+
+fn webdriver_cookie(stored: &StoredCookie) -> Value {
+    let mut cookie = json!({
+        "name": stored.name,
+        "value": stored.value,
+        "secure": stored.secure,
+        "httpOnly": stored.http_only,
+    });
+    if let Some(domain) = &stored.domain {
+        cookie["domain"] = json!(domain);
+    }
+    if let Some(path) = &stored.path {
+        cookie["path"] = json!(path);
+    }
+    if let Some(expires) = stored.expires {
+        cookie["expiry"] = json!(expires);
+    }
+    if let Some(same_site) = &stored.same_site {
+        cookie["sameSite"] = json!(same_site);
+    }
+    cookie
+}
+
+fn cdp_cookie(stored: &StoredCookie) -> Value {
+    let mut cookie = webdriver_cookie(stored);
+    if let Some(expires) = cookie.as_object_mut().and_then(|c| c.remove("expiry")) {
+        cookie["expires"] = expires;
+    }
+    cookie
+}
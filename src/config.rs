@@ -0,0 +1,256 @@
+use crate::agent::history::HistorySharing;
+use crate::browser_control::accessibility::ObservationConfig;
+use crate::browser_control::backend::BackendKind;
+use crate::browser_control::capabilities::BrowserCapabilities;
+use crate::browser_control::profile::ProfileConfig;
+use crate::llm::image::VisionConfig;
+use crate::llm::provider::LlmConfig;
+use crate::logging::LogFormat;
+use crate::memory::embedding::EmbedderKind;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Config file read from the working directory when no `--config` is given.
+pub const DEFAULT_CONFIG_FILE: &str = "oculus.toml";
+
+/// Runtime settings for the agent, loaded from TOML.
+///
+/// Precedence, lowest first: built-in defaults, the config file, `OCULUS_*` environment
+/// variables, then command-line flags (applied by the binary).
+///
+/// ```toml
+/// browser_backend = "webdriver"
+/// webdriver_url = "http://localhost:4444"
+/// max_steps = 25
+/// output_dir = "documents"
+/// headless = true
+/// reuse_browser_sessions = true
+/// memory_file = ".oculus/memory.jsonl"
+/// memory_embedder = "provider"
+/// trace_dir = "traces"
+/// log_level = "info"
+/// log_format = "pretty"
+///
+/// [browser]
+/// window_size = { width = 1280, height = 800 }
+/// locale = "en-US"
+///
+/// [profile]
+/// file = ".oculus/dashboard.profile"
+/// domains = ["dashboard.example.com"]
+///
+/// [vision]
+/// max_size = { width = 1024, height = 768 }
+///
+/// [observation]
+/// mode = "accessibility_tree"
+///
+/// [llm]
+/// provider = "openai"
+/// model = "gpt-4o-mini"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OculusConfig {
+    /// `webdriver` or `playwright`.
+    pub browser_backend: BackendKind,
+    pub webdriver_url: String,
+    /// DevTools endpoint of a running Chromium for the Playwright backend, e.g.
+    /// `http://localhost:9222`. Without it Playwright launches its own browser.
+    pub cdp_url: Option<String>,
+    /// Maximum number of actions each agent may take before giving up.
+    pub max_steps: usize,
+    /// Where generated documents are written.
+    pub output_dir: PathBuf,
+    pub headless: bool,
+    /// Keep a finished step's browser open for the next step instead of closing it.
+    pub reuse_browser_sessions: bool,
+    /// Maximum number of plan steps (and browser sessions) running at once.
+    pub max_concurrency: usize,
+    /// How many times a plan may be revised after a failed step.
+    pub max_replans: usize,
+    /// Which other plan steps' history each agent sees in its prompt.
+    pub history_sharing: HistorySharing,
+    /// Long-term memory kept across runs. An empty path disables memory.
+    pub memory_file: Option<PathBuf>,
+    /// How memories are ranked for the prompt. Vectors are kept next to the memory file
+    /// in `<name>.index.json`.
+    pub memory_embedder: EmbedderKind,
+    /// Where each run writes a JSONL trace of its plans and agent steps. An empty path
+    /// disables tracing.
+    pub trace_dir: Option<PathBuf>,
+    /// Log filter in `RUST_LOG` syntax, e.g. `debug` or `project_oculus=debug,warn`.
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// Browser, window size, user agent, proxy, locale and timezone of new sessions.
+    pub browser: BrowserCapabilities,
+    /// Encrypted cookies and `localStorage` carried between runs. The passphrase comes
+    /// from `OCULUS_PROFILE_KEY`.
+    pub profile: ProfileConfig,
+    /// Whether the annotated screenshot is sent to the model, and how far it is scaled down.
+    pub vision: VisionConfig,
+    /// Whether the page is described by its interactive elements or its accessibility tree.
+    pub observation: ObservationConfig,
+    pub llm: LlmConfig,
+}
+
+impl Default for OculusConfig {
+    fn default() -> Self {
+        OculusConfig {
+            browser_backend: BackendKind::default(),
+            webdriver_url: "http://localhost:4444".to_string(),
+            cdp_url: None,
+            max_steps: 25,
+            output_dir: PathBuf::from("documents"),
+            headless: false,
+            reuse_browser_sessions: true,
+            max_concurrency: 3,
+            max_replans: 2,
+            history_sharing: HistorySharing::default(),
+            memory_file: Some(PathBuf::from(".oculus/memory.jsonl")),
+            memory_embedder: EmbedderKind::default(),
+            trace_dir: Some(PathBuf::from("traces")),
+            log_level: "info".to_string(),
+            log_format: LogFormat::default(),
+            browser: BrowserCapabilities::default(),
+            profile: ProfileConfig::default(),
+            vision: VisionConfig::default(),
+            observation: ObservationConfig::default(),
+            llm: LlmConfig::default(),
+        }
+    }
+}
+
+impl OculusConfig {
+    /// Loads `path`, or [`DEFAULT_CONFIG_FILE`] if it exists, then applies environment
+    /// overrides. An explicitly given path must exist.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let mut config = match path {
+            Some(path) => OculusConfig::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                OculusConfig::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => OculusConfig::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        let config = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    /// Overrides fields with any `OCULUS_*` environment variables that are set.
+    pub fn apply_env(&mut self) -> Result<(), String> {
+        if let Ok(url) = env::var("OCULUS_WEBDRIVER_URL") {
+            self.webdriver_url = url;
+        }
+        if let Ok(url) = env::var("OCULUS_CDP_URL") {
+            self.cdp_url = Some(url).filter(|u| !u.is_empty());
+        }
+        if let Some(backend) = parse_env("OCULUS_BROWSER_BACKEND")? {
+            self.browser_backend = backend;
+        }
+        if let Ok(dir) = env::var("OCULUS_OUTPUT_DIR") {
+            self.output_dir = PathBuf::from(dir);
+        }
+        if let Ok(file) = env::var("OCULUS_MEMORY_FILE") {
+            self.memory_file = Some(file).filter(|f| !f.is_empty()).map(PathBuf::from);
+        }
+        if let Ok(dir) = env::var("OCULUS_TRACE_DIR") {
+            self.trace_dir = Some(dir).filter(|d| !d.is_empty()).map(PathBuf::from);
+        }
+        if let Ok(level) = env::var("OCULUS_LOG_LEVEL") {
+            self.log_level = level;
+        }
+        if let Some(format) = parse_env("OCULUS_LOG_FORMAT")? {
+            self.log_format = format;
+        }
+        if let Some(embedder) = parse_env("OCULUS_MEMORY_EMBEDDER")? {
+            self.memory_embedder = embedder;
+        }
+        if let Some(max_steps) = parse_env("OCULUS_MAX_STEPS")? {
+            self.max_steps = max_steps;
+        }
+        if let Some(headless) = parse_env("OCULUS_HEADLESS")? {
+            self.headless = headless;
+        }
+        if let Some(reuse) = parse_env("OCULUS_REUSE_BROWSER_SESSIONS")? {
+            self.reuse_browser_sessions = reuse;
+        }
+        if let Some(browser) = parse_env("OCULUS_BROWSER")? {
+            self.browser.browser = browser;
+        }
+        if let Some(size) = parse_env("OCULUS_WINDOW_SIZE")? {
+            self.browser.window_size = Some(size);
+        }
+        if let Ok(user_agent) = env::var("OCULUS_USER_AGENT") {
+            self.browser.user_agent = Some(user_agent).filter(|u| !u.is_empty());
+        }
+        if let Ok(proxy) = env::var("OCULUS_PROXY") {
+            self.browser.proxy = Some(proxy).filter(|p| !p.is_empty());
+        }
+        if let Ok(locale) = env::var("OCULUS_LOCALE") {
+            self.browser.locale = Some(locale).filter(|l| !l.is_empty());
+        }
+        if let Ok(timezone) = env::var("OCULUS_TIMEZONE") {
+            self.browser.timezone = Some(timezone).filter(|t| !t.is_empty());
+        }
+        if let Ok(file) = env::var("OCULUS_PROFILE_FILE") {
+            self.profile.file = Some(file).filter(|f| !f.is_empty()).map(PathBuf::from);
+        }
+        if let Ok(domains) = env::var("OCULUS_PROFILE_DOMAINS") {
+            self.profile.domains = domains
+                .split(',')
+                .map(|domain| domain.trim().to_string())
+                .filter(|domain| !domain.is_empty())
+                .collect();
+        }
+        if let Some(enabled) = parse_env("OCULUS_VISION")? {
+            self.vision.enabled = enabled;
+        }
+        if let Some(size) = parse_env("OCULUS_VISION_MAX_SIZE")? {
+            self.vision.max_size = size;
+        }
+        if let Some(quality) = parse_env("OCULUS_VISION_JPEG_QUALITY")? {
+            self.vision.jpeg_quality = quality;
+        }
+        if let Some(mode) = parse_env("OCULUS_OBSERVATION")? {
+            self.observation.mode = mode;
+        }
+        if let Some(budget) = parse_env("OCULUS_OBSERVATION_TOKEN_BUDGET")? {
+            self.observation.token_budget = budget;
+        }
+        if let Some(max_concurrency) = parse_env("OCULUS_MAX_CONCURRENCY")? {
+            self.max_concurrency = max_concurrency;
+        }
+        if let Some(max_replans) = parse_env("OCULUS_MAX_REPLANS")? {
+            self.max_replans = max_replans;
+        }
+        if let Some(sharing) = parse_env("OCULUS_HISTORY_SHARING")? {
+            self.history_sharing = sharing;
+        }
+        self.llm.apply_env()
+    }
+}
+
+fn parse_env<T>(var: &str) -> Result<Option<T>, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match env::var(var) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("Invalid {}: {}", var, e)),
+        Err(_) => Ok(None),
+    }
+}
@@ -1,4 +1,13 @@
+pub mod agent;
 pub mod browser_control;
+pub mod config;
+pub mod llm;
+pub mod logging;
+pub mod memory;
+pub mod prompts;
+pub mod report;
+pub mod session;
+pub mod trace;
 pub mod utils;
 
 pub use crate::utils::get_user_input;
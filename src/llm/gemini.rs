@@ -0,0 +1,189 @@
+use crate::llm::image::ImagePart;
+use crate::llm::provider::{LlmConfig, LlmProvider, LlmResponse, LlmResult, TokenUsage};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+/// Google Gemini `generateContent` REST API.
+pub struct GeminiProvider {
+    config: LlmConfig,
+    client: reqwest::Client,
+}
+
+impl GeminiProvider {
+    pub fn new(config: LlmConfig) -> Self {
+        GeminiProvider {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String> {
+        Ok(self
+            .generate_with_usage(prompt, system_instructions)
+            .await?
+            .text)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &str,
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        self.generate_with_images(prompt, &[], system_instructions)
+            .await
+    }
+
+    async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        let api_key = self
+            .config
+            .api_key()
+            .ok_or("GEMINI_API_KEY environment variable not set")?;
+
+        let url = format!(
+            "{}/models/{}:generateContent?key={}",
+            self.config.base_url(),
+            self.config.model(),
+            api_key
+        );
+
+        let mut parts = vec![json!({ "text": prompt })];
+        parts.extend(images.iter().map(|image| {
+            json!({
+                "inline_data": {
+                    "mime_type": image.mime_type,
+                    "data": image.base64()
+                }
+            })
+        }));
+        let mut payload = json!({
+            "contents": [
+                {
+                    "parts": parts
+                }
+            ]
+        });
+        if !system_instructions.is_empty() {
+            payload["system_instruction"] = json!({
+                "parts": [
+                    {
+                        "text": system_instructions
+                    }
+                ]
+            });
+        }
+        if let Some(temperature) = self.config.temperature {
+            payload["generationConfig"] = json!({ "temperature": temperature });
+        }
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("API request failed with status: {}", response.status()).into());
+        }
+
+        let response_json: Value = response.json().await?;
+
+        // Extract the generated text from the response
+        let generated_text = response_json
+            .get("candidates")
+            .and_then(|candidates| candidates.get(0))
+            .and_then(|candidate| candidate.get("content"))
+            .and_then(|content| content.get("parts"))
+            .and_then(|parts| parts.get(0))
+            .and_then(|part| part.get("text"))
+            .and_then(|text| text.as_str())
+            .ok_or("Gemini response did not contain any generated text")?;
+
+        let usage = response_json.get("usageMetadata").map(|usage| TokenUsage {
+            prompt_tokens: usage.get("promptTokenCount").and_then(Value::as_u64),
+            completion_tokens: usage.get("candidatesTokenCount").and_then(Value::as_u64),
+            total_tokens: usage.get("totalTokenCount").and_then(Value::as_u64),
+        });
+
+        Ok(LlmResponse {
+            text: generated_text.to_string(),
+            usage,
+        })
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let api_key = self
+            .config
+            .api_key()
+            .ok_or("GEMINI_API_KEY environment variable not set")?;
+        let model = self.config.embedding_model();
+
+        let url = format!(
+            "{}/models/{}:batchEmbedContents?key={}",
+            self.config.base_url(),
+            model,
+            api_key
+        );
+        let requests: Vec<Value> = texts
+            .iter()
+            .map(|text| {
+                json!({
+                    "model": format!("models/{}", model),
+                    "content": { "parts": [{ "text": text }] }
+                })
+            })
+            .collect();
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Embedding request failed with status: {}",
+                response.status()
+            )
+            .into());
+        }
+
+        let response_json: Value = response.json().await?;
+
+        let embeddings = response_json
+            .get("embeddings")
+            .and_then(|embeddings| embeddings.as_array())
+            .ok_or("Gemini response did not contain any embeddings")?;
+        Ok(embeddings
+            .iter()
+            .map(|embedding| {
+                embedding
+                    .get("values")
+                    .and_then(|values| values.as_array())
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(Value::as_f64)
+                            .map(|v| v as f32)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+}
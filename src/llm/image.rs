@@ -0,0 +1,81 @@
+use crate::browser_control::capabilities::WindowSize;
+use base64::Engine;
+use image::ImageFormat;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+
+/// An image sent to the model alongside the text prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePart {
+    /// e.g. `image/jpeg`.
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ImagePart {
+    pub fn new(mime_type: impl Into<String>, data: Vec<u8>) -> Self {
+        ImagePart {
+            mime_type: mime_type.into(),
+            data,
+        }
+    }
+
+    pub fn base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(&self.data)
+    }
+
+    /// `data:<mime type>;base64,...`, for APIs that take images as URLs.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.base64())
+    }
+}
+
+/// Whether and how the annotated page screenshot is attached to the agent's prompt. Read
+/// from the `[vision]` table of the config file:
+///
+/// ```toml
+/// [vision]
+/// enabled = true
+/// max_size = { width = 1024, height = 1024 }
+/// jpeg_quality = 75
+/// ```
+///
+/// Screenshots are scaled down to fit `max_size`, keeping their aspect ratio, and sent as
+/// JPEG. The PNGs saved for traces and reports stay at full resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VisionConfig {
+    pub enabled: bool,
+    pub max_size: WindowSize,
+    /// 1 (smallest) to 100 (best).
+    pub jpeg_quality: u8,
+}
+
+impl Default for VisionConfig {
+    fn default() -> Self {
+        VisionConfig {
+            enabled: true,
+            max_size: WindowSize {
+                width: 1024,
+                height: 1024,
+            },
+            jpeg_quality: 75,
+        }
+    }
+}
+
+impl VisionConfig {
+    /// Downscales a PNG screenshot and re-encodes it as JPEG for the model.
+    pub fn encode_screenshot(&self, png: &[u8]) -> Result<ImagePart, image::ImageError> {
+        let mut image = image::load_from_memory_with_format(png, ImageFormat::Png)?;
+        let WindowSize { width, height } = self.max_size;
+        if image.width() > width || image.height() > height {
+            image = image.resize(width.max(1), height.max(1), FilterType::Triangle);
+        }
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, self.jpeg_quality.clamp(1, 100))
+            .encode_image(&image.to_rgb8())?;
+        Ok(ImagePart::new("image/jpeg", jpeg))
+    }
+}
@@ -0,0 +1,6 @@
+pub mod gemini;
+pub mod image;
+pub mod ollama;
+pub mod openai;
+pub mod provider;
+pub mod scripted;
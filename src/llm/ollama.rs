@@ -0,0 +1,140 @@
+use crate::llm::image::ImagePart;
+use crate::llm::provider::{LlmConfig, LlmProvider, LlmResponse, LlmResult, TokenUsage};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+/// A local Ollama-style server exposing `/api/chat`.
+pub struct OllamaProvider {
+    config: LlmConfig,
+    client: reqwest::Client,
+}
+
+impl OllamaProvider {
+    pub fn new(config: LlmConfig) -> Self {
+        OllamaProvider {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String> {
+        Ok(self
+            .generate_with_usage(prompt, system_instructions)
+            .await?
+            .text)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &str,
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        self.generate_with_images(prompt, &[], system_instructions)
+            .await
+    }
+
+    async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        let url = format!("{}/api/chat", self.config.base_url());
+
+        let mut messages = Vec::new();
+        if !system_instructions.is_empty() {
+            messages.push(json!({ "role": "system", "content": system_instructions }));
+        }
+        let mut message = json!({ "role": "user", "content": prompt });
+        if !images.is_empty() {
+            // Ollama takes bare base64 and works out the format itself.
+            message["images"] = images.iter().map(ImagePart::base64).collect();
+        }
+        messages.push(message);
+
+        let mut payload = json!({
+            "model": self.config.model(),
+            "messages": messages,
+            "stream": false,
+        });
+        if let Some(temperature) = self.config.temperature {
+            payload["options"] = json!({ "temperature": temperature });
+        }
+
+        let response = self.client.post(&url).json(&payload).send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!("API request failed with status: {}", response.status()).into());
+        }
+
+        let response_json: Value = response.json().await?;
+
+        let generated_text = response_json
+            .get("message")
+            .and_then(|message| message.get("content"))
+            .and_then(|content| content.as_str())
+            .ok_or("Ollama response did not contain any message content")?;
+
+        let prompt_tokens = response_json
+            .get("prompt_eval_count")
+            .and_then(Value::as_u64);
+        let completion_tokens = response_json.get("eval_count").and_then(Value::as_u64);
+        let usage = (prompt_tokens.is_some() || completion_tokens.is_some()).then(|| TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens.zip(completion_tokens).map(|(p, c)| p + c),
+        });
+
+        Ok(LlmResponse {
+            text: generated_text.to_string(),
+            usage,
+        })
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.config.base_url());
+        let payload = json!({
+            "model": self.config.embedding_model(),
+            "input": texts,
+        });
+
+        let response = self.client.post(&url).json(&payload).send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Embedding request failed with status: {}",
+                response.status()
+            )
+            .into());
+        }
+
+        let response_json: Value = response.json().await?;
+
+        let embeddings = response_json
+            .get("embeddings")
+            .and_then(|embeddings| embeddings.as_array())
+            .ok_or("Ollama response did not contain any embeddings")?;
+        Ok(embeddings
+            .iter()
+            .map(|embedding| {
+                embedding
+                    .as_array()
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(Value::as_f64)
+                            .map(|v| v as f32)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+}
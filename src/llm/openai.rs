@@ -0,0 +1,159 @@
+use crate::llm::image::ImagePart;
+use crate::llm::provider::{LlmConfig, LlmProvider, LlmResponse, LlmResult, TokenUsage};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+/// Any server implementing the OpenAI `/chat/completions` endpoint.
+///
+/// The API key is optional so self-hosted servers (vLLM, LM Studio, llama.cpp) work
+/// without one.
+pub struct OpenAiProvider {
+    config: LlmConfig,
+    client: reqwest::Client,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: LlmConfig) -> Self {
+        OpenAiProvider {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String> {
+        Ok(self
+            .generate_with_usage(prompt, system_instructions)
+            .await?
+            .text)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &str,
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        self.generate_with_images(prompt, &[], system_instructions)
+            .await
+    }
+
+    async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        let url = format!("{}/chat/completions", self.config.base_url());
+
+        let mut messages = Vec::new();
+        if !system_instructions.is_empty() {
+            messages.push(json!({ "role": "system", "content": system_instructions }));
+        }
+        if images.is_empty() {
+            messages.push(json!({ "role": "user", "content": prompt }));
+        } else {
+            let mut content = vec![json!({ "type": "text", "text": prompt })];
+            content.extend(images.iter().map(
+                |image| json!({ "type": "image_url", "image_url": { "url": image.data_url() } }),
+            ));
+            messages.push(json!({ "role": "user", "content": content }));
+        }
+
+        let mut payload = json!({
+            "model": self.config.model(),
+            "messages": messages,
+        });
+        if let Some(temperature) = self.config.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+
+        let mut request = self.client.post(&url).json(&payload);
+        if let Some(api_key) = self.config.api_key() {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!("API request failed with status: {}", response.status()).into());
+        }
+
+        let response_json: Value = response.json().await?;
+
+        let generated_text = response_json
+            .get("choices")
+            .and_then(|choices| choices.get(0))
+            .and_then(|choice| choice.get("message"))
+            .and_then(|message| message.get("content"))
+            .and_then(|content| content.as_str())
+            .ok_or("Chat completion response did not contain any message content")?;
+
+        let usage = response_json.get("usage").map(|usage| TokenUsage {
+            prompt_tokens: usage.get("prompt_tokens").and_then(Value::as_u64),
+            completion_tokens: usage.get("completion_tokens").and_then(Value::as_u64),
+            total_tokens: usage.get("total_tokens").and_then(Value::as_u64),
+        });
+
+        Ok(LlmResponse {
+            text: generated_text.to_string(),
+            usage,
+        })
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", self.config.base_url());
+        let payload = json!({
+            "model": self.config.embedding_model(),
+            "input": texts,
+        });
+
+        let mut request = self.client.post(&url).json(&payload);
+        if let Some(api_key) = self.config.api_key() {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Embedding request failed with status: {}",
+                response.status()
+            )
+            .into());
+        }
+
+        let response_json: Value = response.json().await?;
+
+        let mut data: Vec<(usize, Vec<f32>)> = response_json
+            .get("data")
+            .and_then(|data| data.as_array())
+            .ok_or("Embedding response did not contain any data")?
+            .iter()
+            .enumerate()
+            .map(|(position, item)| {
+                let index = item
+                    .get("index")
+                    .and_then(Value::as_u64)
+                    .map_or(position, |index| index as usize);
+                let vector = item
+                    .get("embedding")
+                    .and_then(|embedding| embedding.as_array())
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(Value::as_f64)
+                            .map(|v| v as f32)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (index, vector)
+            })
+            .collect();
+        data.sort_by_key(|(index, _)| *index);
+        Ok(data.into_iter().map(|(_, vector)| vector).collect())
+    }
+}
@@ -0,0 +1,283 @@
+use crate::llm::gemini::GeminiProvider;
+use crate::llm::image::ImagePart;
+use crate::llm::ollama::OllamaProvider;
+use crate::llm::openai::OpenAiProvider;
+use crate::llm::scripted::ScriptedProvider;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::{debug, warn};
+
+/// Error type returned by providers. `Send + Sync` so responses can cross task boundaries.
+pub type LlmError = Box<dyn std::error::Error + Send + Sync>;
+pub type LlmResult<T> = Result<T, LlmError>;
+
+/// A backend capable of turning a prompt (plus optional system instructions) into text.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Short identifier used in logs, e.g. "gemini".
+    fn name(&self) -> &str;
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String>;
+
+    /// Like [`LlmProvider::generate`], plus token counts when the backend reports them.
+    async fn generate_with_usage(
+        &self,
+        prompt: &str,
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        Ok(LlmResponse {
+            text: self.generate(prompt, system_instructions).await?,
+            usage: None,
+        })
+    }
+
+    /// Like [`LlmProvider::generate_with_usage`], with images attached after the prompt.
+    /// Providers that can't take images answer from the text alone.
+    async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        if !images.is_empty() {
+            debug!(
+                "The {} provider does not take images; sending the prompt without {} image(s)",
+                self.name(),
+                images.len()
+            );
+        }
+        self.generate_with_usage(prompt, system_instructions).await
+    }
+
+    /// Embeds each text into a vector, in order. Providers without an embedding endpoint
+    /// return an error.
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let _ = texts;
+        Err(format!("The {} provider does not support embeddings", self.name()).into())
+    }
+}
+
+/// Token counts reported by a provider. Fields a backend doesn't report are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub total_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LlmResponse {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Gemini,
+    /// Any endpoint speaking the OpenAI `/chat/completions` protocol (OpenAI, vLLM, LM Studio, ...).
+    #[serde(alias = "openai")]
+    OpenAi,
+    Ollama,
+    /// Offline replay of canned responses from `script_path`, for tests.
+    Scripted,
+}
+
+impl std::str::FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "gemini" => Ok(ProviderKind::Gemini),
+            "openai" | "open_ai" | "openai_compatible" => Ok(ProviderKind::OpenAi),
+            "ollama" => Ok(ProviderKind::Ollama),
+            "scripted" | "mock" => Ok(ProviderKind::Scripted),
+            other => Err(format!("Unknown LLM provider: {}", other)),
+        }
+    }
+}
+
+/// Provider selection and connection settings.
+///
+/// Every field is optional in serialized form; missing values fall back to the
+/// provider's defaults (see [`LlmConfig::model`] and [`LlmConfig::base_url`]).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmConfig {
+    pub provider: ProviderKind,
+    pub model: Option<String>,
+    /// Model used by [`LlmProvider::embed`].
+    pub embedding_model: Option<String>,
+    pub base_url: Option<String>,
+    pub api_key: Option<String>,
+    /// Name of the environment variable holding the API key, if not given inline.
+    pub api_key_env: Option<String>,
+    pub temperature: Option<f32>,
+    /// JSON file of canned responses, used by the `scripted` provider.
+    pub script_path: Option<String>,
+}
+
+impl LlmConfig {
+    /// Builds a config from `OCULUS_LLM_*` environment variables, defaulting to Gemini.
+    pub fn from_env() -> Result<Self, String> {
+        let mut config = LlmConfig::default();
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Overrides fields with any `OCULUS_LLM_*` environment variables that are set.
+    pub fn apply_env(&mut self) -> Result<(), String> {
+        if let Ok(value) = env::var("OCULUS_LLM_PROVIDER")
+            && !value.trim().is_empty()
+        {
+            self.provider = value.parse()?;
+        }
+        if let Ok(value) = env::var("OCULUS_LLM_TEMPERATURE") {
+            self.temperature = Some(
+                value
+                    .parse::<f32>()
+                    .map_err(|e| format!("Invalid OCULUS_LLM_TEMPERATURE: {}", e))?,
+            );
+        }
+        if let Ok(model) = env::var("OCULUS_LLM_MODEL") {
+            self.model = Some(model);
+        }
+        if let Ok(model) = env::var("OCULUS_LLM_EMBEDDING_MODEL") {
+            self.embedding_model = Some(model);
+        }
+        if let Ok(base_url) = env::var("OCULUS_LLM_BASE_URL") {
+            self.base_url = Some(base_url);
+        }
+        if let Ok(api_key) = env::var("OCULUS_LLM_API_KEY") {
+            self.api_key = Some(api_key);
+        }
+        if let Ok(script_path) = env::var("OCULUS_LLM_SCRIPT") {
+            self.script_path = Some(script_path);
+        }
+        Ok(())
+    }
+
+    pub fn model(&self) -> String {
+        if let Some(model) = &self.model {
+            return model.clone();
+        }
+        match self.provider {
+            ProviderKind::Gemini => "gemini-2.0-flash",
+            ProviderKind::OpenAi => "gpt-4o-mini",
+            ProviderKind::Ollama => "llama3.1",
+            ProviderKind::Scripted => "scripted",
+        }
+        .to_string()
+    }
+
+    pub fn embedding_model(&self) -> String {
+        if let Some(model) = &self.embedding_model {
+            return model.clone();
+        }
+        match self.provider {
+            ProviderKind::Gemini => "text-embedding-004",
+            ProviderKind::OpenAi => "text-embedding-3-small",
+            ProviderKind::Ollama => "nomic-embed-text",
+            ProviderKind::Scripted => "scripted",
+        }
+        .to_string()
+    }
+
+    pub fn base_url(&self) -> String {
+        let url = match &self.base_url {
+            Some(url) => url.as_str(),
+            None => match self.provider {
+                ProviderKind::Gemini => "https://generativelanguage.googleapis.com/v1beta",
+                ProviderKind::OpenAi => "https://api.openai.com/v1",
+                ProviderKind::Ollama => "http://localhost:11434",
+                ProviderKind::Scripted => "",
+            },
+        };
+        url.trim_end_matches('/').to_string()
+    }
+
+    /// Resolves the API key: inline value first, then `api_key_env`, then the provider's
+    /// conventional variable (`GEMINI_API_KEY` / `OPENAI_API_KEY`). Ollama needs none.
+    pub fn api_key(&self) -> Option<String> {
+        if let Some(key) = &self.api_key {
+            return Some(key.clone());
+        }
+        let var = match (&self.api_key_env, self.provider) {
+            (Some(var), _) => var.as_str(),
+            (None, ProviderKind::Gemini) => "GEMINI_API_KEY",
+            (None, ProviderKind::OpenAi) => "OPENAI_API_KEY",
+            (None, ProviderKind::Ollama | ProviderKind::Scripted) => return None,
+        };
+        env::var(var).ok().filter(|key| !key.is_empty())
+    }
+}
+
+/// Instantiates the provider described by `config`.
+pub fn build_provider(
+    config: &LlmConfig,
+) -> Result<Arc<dyn LlmProvider>, Box<dyn std::error::Error>> {
+    let provider: Arc<dyn LlmProvider> = match config.provider {
+        ProviderKind::Gemini => Arc::new(GeminiProvider::new(config.clone())),
+        ProviderKind::OpenAi => Arc::new(OpenAiProvider::new(config.clone())),
+        ProviderKind::Ollama => Arc::new(OllamaProvider::new(config.clone())),
+        ProviderKind::Scripted => {
+            let path = config
+                .script_path
+                .as_deref()
+                .ok_or("The scripted provider requires a script_path (OCULUS_LLM_SCRIPT)")?;
+            Arc::new(ScriptedProvider::from_file(path)?)
+        }
+    };
+    Ok(provider)
+}
+
+/// Stands in for a provider the environment asked for but that couldn't be built, so a
+/// misconfigured run fails on its first call instead of quietly using another backend.
+struct UnavailableProvider {
+    reason: String,
+}
+
+#[async_trait]
+impl LlmProvider for UnavailableProvider {
+    fn name(&self) -> &str {
+        "unavailable"
+    }
+
+    async fn generate(&self, _prompt: &str, _system_instructions: &str) -> LlmResult<String> {
+        Err(format!("No LLM provider: {}", self.reason).into())
+    }
+
+    async fn embed(&self, _texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        Err(format!("No LLM provider: {}", self.reason).into())
+    }
+}
+
+static ACTIVE_PROVIDER: OnceLock<RwLock<Arc<dyn LlmProvider>>> = OnceLock::new();
+
+fn active_provider() -> &'static RwLock<Arc<dyn LlmProvider>> {
+    ACTIVE_PROVIDER.get_or_init(|| {
+        let provider = LlmConfig::from_env()
+            .map_err(|e| e.into())
+            .and_then(|config| build_provider(&config))
+            .unwrap_or_else(|e| {
+                warn!("{}. Every LLM call will fail.", e);
+                Arc::new(UnavailableProvider {
+                    reason: e.to_string(),
+                })
+            });
+        RwLock::new(provider)
+    })
+}
+
+/// Replaces the provider used by [`crate::utils::generate_ai_response`].
+pub fn set_provider(provider: Arc<dyn LlmProvider>) {
+    *active_provider().write().unwrap() = provider;
+}
+
+/// Returns the provider currently in use, initialising it from the environment on first use.
+pub fn current_provider() -> Arc<dyn LlmProvider> {
+    active_provider().read().unwrap().clone()
+}
@@ -0,0 +1,113 @@
+use crate::llm::image::ImagePart;
+use crate::llm::provider::{LlmProvider, LlmResponse, LlmResult};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A prompt received by [`ScriptedProvider`], in call order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedPrompt {
+    pub prompt: String,
+    pub system_instructions: String,
+    /// MIME types of the images attached to the prompt.
+    pub images: Vec<String>,
+}
+
+/// Offline provider that replays a fixed sequence of responses and records every prompt.
+///
+/// Responses are returned in order, one per call; once the script is exhausted every
+/// further call fails so a test notices the agent asked for more than was scripted.
+pub struct ScriptedProvider {
+    responses: Mutex<VecDeque<String>>,
+    prompts: Mutex<Vec<RecordedPrompt>>,
+}
+
+impl ScriptedProvider {
+    pub fn new<I, S>(responses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ScriptedProvider {
+            responses: Mutex::new(responses.into_iter().map(Into::into).collect()),
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Loads a script from a JSON file containing an array of responses.
+    ///
+    /// String entries are replayed verbatim; any other JSON value (typically an object
+    /// in the agent's response format) is serialized before being replayed.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let raw = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read script {}: {}", path.display(), e))?;
+        let entries: Vec<Value> = serde_json::from_str(&raw)
+            .map_err(|e| format!("Script {} is not a JSON array: {}", path.display(), e))?;
+
+        let responses = entries.into_iter().map(|entry| match entry {
+            Value::String(s) => s,
+            other => other.to_string(),
+        });
+        Ok(ScriptedProvider::new(responses))
+    }
+
+    /// Every prompt received so far.
+    pub fn recorded_prompts(&self) -> Vec<RecordedPrompt> {
+        self.prompts.lock().unwrap().clone()
+    }
+
+    /// Number of scripted responses not yet consumed.
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    /// Records the prompt and pops the next response.
+    fn respond(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<String> {
+        let mut prompts = self.prompts.lock().unwrap();
+        prompts.push(RecordedPrompt {
+            prompt: prompt.to_string(),
+            system_instructions: system_instructions.to_string(),
+            images: images.iter().map(|image| image.mime_type.clone()).collect(),
+        });
+
+        self.responses.lock().unwrap().pop_front().ok_or_else(|| {
+            format!(
+                "Scripted provider exhausted: no response left for call {}",
+                prompts.len()
+            )
+            .into()
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ScriptedProvider {
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String> {
+        self.respond(prompt, &[], system_instructions)
+    }
+
+    async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        Ok(LlmResponse {
+            text: self.respond(prompt, images, system_instructions)?,
+            usage: None,
+        })
+    }
+}
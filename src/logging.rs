@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use tracing_subscriber::EnvFilter;
+
+/// How log lines are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines prefixed with the enclosing spans.
+    #[default]
+    Pretty,
+    /// One JSON object per line, with the current span and its parents.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pretty" | "text" | "human" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("Unknown log format: {}", other)),
+        }
+    }
+}
+
+/// Installs a global `tracing` subscriber writing to stderr.
+///
+/// `filter` uses the `RUST_LOG` directive syntax, e.g. `info` or
+/// `project_oculus=debug,thirtyfour=warn`; `RUST_LOG` itself takes precedence when set.
+/// Library users who already have a subscriber should not call this: every span and event
+/// is emitted through `tracing` and picked up by whatever subscriber is installed.
+pub fn init_logging(format: LogFormat, filter: &str) -> Result<(), Box<dyn Error>> {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) if !directives.trim().is_empty() => EnvFilter::try_new(directives)?,
+        _ => EnvFilter::try_new(filter)
+            .map_err(|e| format!("Invalid log filter {:?}: {}", filter, e))?,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    let result = match format {
+        LogFormat::Pretty => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+    };
+    result.map_err(|e| format!("Failed to install the log subscriber: {}", e).into())
+}
@@ -1,124 +1,581 @@
-mod agent;
-mod prompts;
-mod utils;
+use clap::{Args, Parser, Subcommand};
+use project_oculus::agent::history::HistorySharing;
+use project_oculus::agent::plan_executor::{PlanExecutor, Replanning};
+use project_oculus::agent::plan_validator::validate_plan;
+use project_oculus::agent::planner::{MAX_PLAN_REPAIR_ATTEMPTS, PlannerAgentPlan, planner_agent};
+use project_oculus::agent::registry::ActionRegistry;
+use project_oculus::browser_control::accessibility::ObservationMode;
+use project_oculus::browser_control::backend::{BackendKind, BrowserOptions};
+use project_oculus::browser_control::capabilities::{BrowserKind, WindowSize};
+use project_oculus::browser_control::profile::BrowserProfile;
+use project_oculus::browser_control::session_manager::BrowserSessionManager;
+use project_oculus::config::OculusConfig;
+use project_oculus::get_user_input;
+use project_oculus::llm::provider::{ProviderKind, build_provider, current_provider, set_provider};
+use project_oculus::logging::{LogFormat, init_logging};
+use project_oculus::memory::embedding::{Embedder, EmbedderKind, HashEmbedder, ProviderEmbedder};
+use project_oculus::memory::memory_manager::MemoryManager;
+use project_oculus::memory::retrieval::SemanticRetriever;
+use project_oculus::report::{RunReport, report_path};
+use project_oculus::session::recorder::{RecordingProvider, SessionRecorder};
+use project_oculus::session::replay::{ReplayProvider, SessionArchive};
+use project_oculus::trace::TraceWriter;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{error, info, warn};
 
-use thirtyfour::common::print;
+#[derive(Parser)]
+#[command(
+    name = "oculus",
+    version,
+    about = "Plan and run browser tasks with LLM agents"
+)]
+struct Cli {
+    /// TOML config file. Defaults to ./oculus.toml if it exists.
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Log more detail: -v for debug, -vv for trace.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log less: -q for warnings only, -qq for errors only.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+
+    /// Log format on stderr: pretty or json.
+    #[arg(long, global = true, value_name = "FORMAT")]
+    log_format: Option<LogFormat>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+impl Cli {
+    /// The log filter implied by `-v`/`-q`, if either was given.
+    fn log_level(&self) -> Option<&'static str> {
+        match (self.verbose, self.quiet) {
+            (0, 0) => None,
+            (0, 1) => Some("warn"),
+            (0, _) => Some("error"),
+            (1, _) => Some("oculus=debug,project_oculus=debug,info"),
+            _ => Some("oculus=trace,project_oculus=trace,debug"),
+        }
+    }
+
+    /// Applies the logging flags to `config` and installs the subscriber.
+    fn init_logging(&self, config: &mut OculusConfig) -> Result<(), Box<dyn Error>> {
+        if let Some(level) = self.log_level() {
+            config.log_level = level.to_string();
+        }
+        if let Some(format) = self.log_format {
+            config.log_format = format;
+        }
+        init_logging(config.log_format, &config.log_level)
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Plan a task and execute the plan.
+    Run {
+        /// The task to perform. Prompted for on stdin if omitted.
+        task: Option<String>,
+        /// Execute a plan saved by `oculus plan` instead of planning the task.
+        #[arg(long, value_name = "FILE")]
+        plan: Option<PathBuf>,
+        #[command(flatten)]
+        settings: Settings,
+    },
+    /// Plan a task and print the plan without executing it.
+    Plan {
+        /// The task to plan. Prompted for on stdin if omitted.
+        task: Option<String>,
+        /// Also write the plan as JSON to this file.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        settings: Settings,
+    },
+    /// Render the HTML report of a run from its trace.
+    Report {
+        /// JSONL trace written by `oculus run`.
+        trace: PathBuf,
+        /// Where to write the report. Defaults to the trace path with an .html extension.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Overrides for values from the config file and environment.
+#[derive(Args)]
+struct Settings {
+    /// Browser backend: webdriver or playwright.
+    #[arg(long)]
+    backend: Option<BackendKind>,
+    #[arg(long, value_name = "URL")]
+    webdriver_url: Option<String>,
+    /// Attach the Playwright backend to a running Chromium's DevTools endpoint.
+    #[arg(long, value_name = "URL")]
+    cdp_url: Option<String>,
+    /// LLM provider: gemini, openai, ollama or scripted.
+    #[arg(long)]
+    provider: Option<ProviderKind>,
+    #[arg(long)]
+    model: Option<String>,
+    /// Maximum number of actions per agent.
+    #[arg(long)]
+    max_steps: Option<usize>,
+    /// Directory for generated documents.
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+    /// Run the browser without a visible window.
+    #[arg(long)]
+    headless: bool,
+    /// Start a fresh browser for every plan step instead of reusing finished steps' sessions.
+    #[arg(long)]
+    no_session_reuse: bool,
+    /// Browser to start: chrome or firefox.
+    #[arg(long)]
+    browser: Option<BrowserKind>,
+    /// Window and viewport size, e.g. 1280x800.
+    #[arg(long, value_name = "WIDTHxHEIGHT")]
+    window_size: Option<WindowSize>,
+    #[arg(long)]
+    user_agent: Option<String>,
+    /// Proxy for all browser traffic, e.g. http://host:3128.
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+    /// Browser language, e.g. en-US.
+    #[arg(long)]
+    locale: Option<String>,
+    /// IANA timezone, e.g. Europe/Berlin.
+    #[arg(long)]
+    timezone: Option<String>,
+    /// Encrypted browser profile to restore cookies and localStorage from and save them to
+    /// (passphrase in OCULUS_PROFILE_KEY).
+    #[arg(long, value_name = "FILE")]
+    profile: Option<PathBuf>,
+    /// Domain whose cookies and localStorage the profile keeps. Repeatable.
+    #[arg(long = "profile-domain", value_name = "DOMAIN")]
+    profile_domains: Vec<String>,
+    /// Restore the profile but don't write the run's state back to it.
+    #[arg(long)]
+    no_save_profile: bool,
+    /// Extra browser command-line argument. Repeatable.
+    #[arg(long = "browser-arg", value_name = "ARG", allow_hyphen_values = true)]
+    browser_args: Vec<String>,
+    /// Don't attach the annotated page screenshot to the agent's prompt.
+    #[arg(long)]
+    no_vision: bool,
+    /// Largest screenshot sent to the model; bigger ones are scaled down, e.g. 1024x768.
+    #[arg(long, value_name = "WIDTHxHEIGHT")]
+    vision_max_size: Option<WindowSize>,
+    /// How the page is shown to the agent: elements or accessibility_tree.
+    #[arg(long, value_name = "MODE")]
+    observation: Option<ObservationMode>,
+    /// Rough token limit for the accessibility tree in each prompt.
+    #[arg(long, value_name = "TOKENS")]
+    observation_token_budget: Option<usize>,
+    #[arg(long)]
+    max_concurrency: Option<usize>,
+    #[arg(long)]
+    max_replans: Option<usize>,
+    /// Which other steps' history each agent sees: isolated, dependencies or run.
+    #[arg(long, value_name = "MODE")]
+    history_sharing: Option<HistorySharing>,
+    /// Long-term memory file shared across runs.
+    #[arg(long, value_name = "FILE")]
+    memory_file: Option<PathBuf>,
+    /// Don't read or write long-term memory.
+    #[arg(long, conflicts_with = "memory_file")]
+    no_memory: bool,
+    /// How memories are ranked for the prompt: provider, hash or none (keyword matching).
+    #[arg(long, value_name = "KIND")]
+    memory_embedder: Option<EmbedderKind>,
+    /// Directory for the JSONL trace of each run.
+    #[arg(long, value_name = "DIR")]
+    trace_dir: Option<PathBuf>,
+    /// Don't write a run trace.
+    #[arg(long, conflicts_with = "trace_dir")]
+    no_trace: bool,
+    /// Record the run into a session archive (also OCULUS_RECORD_DIR).
+    #[arg(long, value_name = "DIR")]
+    record: Option<PathBuf>,
+    /// Replay a recorded session with its recorded LLM responses (also OCULUS_REPLAY_DIR).
+    #[arg(long, value_name = "DIR")]
+    replay: Option<PathBuf>,
+}
+
+impl Settings {
+    fn apply(&self, config: &mut OculusConfig) {
+        if let Some(backend) = self.backend {
+            config.browser_backend = backend;
+        }
+        if let Some(url) = &self.webdriver_url {
+            config.webdriver_url = url.clone();
+        }
+        if let Some(url) = &self.cdp_url {
+            config.cdp_url = Some(url.clone());
+        }
+        if let Some(provider) = self.provider {
+            config.llm.provider = provider;
+        }
+        if let Some(model) = &self.model {
+            config.llm.model = Some(model.clone());
+        }
+        if let Some(max_steps) = self.max_steps {
+            config.max_steps = max_steps;
+        }
+        if let Some(dir) = &self.output_dir {
+            config.output_dir = dir.clone();
+        }
+        if self.headless {
+            config.headless = true;
+        }
+        if self.no_session_reuse {
+            config.reuse_browser_sessions = false;
+        }
+        if let Some(browser) = self.browser {
+            config.browser.browser = browser;
+        }
+        if let Some(size) = self.window_size {
+            config.browser.window_size = Some(size);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            config.browser.user_agent = Some(user_agent.clone());
+        }
+        if let Some(proxy) = &self.proxy {
+            config.browser.proxy = Some(proxy.clone());
+        }
+        if let Some(locale) = &self.locale {
+            config.browser.locale = Some(locale.clone());
+        }
+        if let Some(timezone) = &self.timezone {
+            config.browser.timezone = Some(timezone.clone());
+        }
+        config
+            .browser
+            .args
+            .extend(self.browser_args.iter().cloned());
+        if let Some(file) = &self.profile {
+            config.profile.file = Some(file.clone());
+        }
+        if !self.profile_domains.is_empty() {
+            config.profile.domains = self.profile_domains.clone();
+        }
+        if self.no_save_profile {
+            config.profile.save = false;
+        }
+        if self.no_vision {
+            config.vision.enabled = false;
+        }
+        if let Some(size) = self.vision_max_size {
+            config.vision.max_size = size;
+        }
+        if let Some(mode) = self.observation {
+            config.observation.mode = mode;
+        }
+        if let Some(budget) = self.observation_token_budget {
+            config.observation.token_budget = budget;
+        }
+        if let Some(max_concurrency) = self.max_concurrency {
+            config.max_concurrency = max_concurrency;
+        }
+        if let Some(max_replans) = self.max_replans {
+            config.max_replans = max_replans;
+        }
+        if let Some(sharing) = self.history_sharing {
+            config.history_sharing = sharing;
+        }
+        if let Some(file) = &self.memory_file {
+            config.memory_file = Some(file.clone());
+        }
+        if self.no_memory {
+            config.memory_file = None;
+        }
+        if let Some(embedder) = self.memory_embedder {
+            config.memory_embedder = embedder;
+        }
+        if let Some(dir) = &self.trace_dir {
+            config.trace_dir = Some(dir.clone());
+        }
+        if self.no_trace {
+            config.trace_dir = None;
+        }
+    }
+
+    fn record_dir(&self) -> Option<PathBuf> {
+        self.record
+            .clone()
+            .or_else(|| std::env::var_os("OCULUS_RECORD_DIR").map(PathBuf::from))
+    }
+
+    fn replay_dir(&self) -> Option<PathBuf> {
+        self.replay
+            .clone()
+            .or_else(|| std::env::var_os("OCULUS_REPLAY_DIR").map(PathBuf::from))
+    }
+}
 
-use crate::agent::planner::{AgentStep, PlannerAgentPlan, planner_agent};
-use std::error::Error;
-use thirtyfour::WebDriver;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    println!("Application starting...");
-
-    let planner_response = planner_agent().await;
-    match planner_response {
-        Ok(response) => {
-            println!("Planner AI Response: {}", response);
-            // Debug: print the raw response before parsing
-            println!("Raw planner response for debug: {:?}", response);
-            // If the response is already a JSON object, use it directly
-            let cleaned_response = response
-                .trim_start_matches("```json")
-                .trim_start_matches("```")
-                .trim_end_matches("```")
-                .trim()
-                .to_string();
-
-            println!("Cleaned planner response for debug: {:?}", cleaned_response);
-            // Parse the plan
-            let plan: PlannerAgentPlan = match serde_json::from_str(&cleaned_response) {
-                Ok(p) => p,
-                Err(e) => {
-                    eprintln!(
-                        "Failed to parse planner response as plan: {}\nResponse was: {}",
-                        e, cleaned_response
-                    );
-                    return Err(e.into());
-                }
-            };
-            // Store outputs for context passing
-            let mut agent_outputs: Vec<Option<String>> = vec![None; plan.steps.len()];
-            for (i, step) in plan.steps.iter().enumerate() {
-                // Try to extract agent parameters from top-level fields, fallback to parameters object
-                let id = step.id.clone();
-                let goal = step
-                    .parameters
-                    .get("goal")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let description = step
-                    .parameters
-                    .get("description")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let tools = step
-                    .parameters
-                    .get("tools")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let role = step
-                    .parameters
-                    .get("role")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let backstory = step
-                    .parameters
-                    .get("backstory")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let agent_context = if let Some(ctx_idx) = step.needs_context_from {
-                    agent_outputs
-                        .get(ctx_idx)
-                        .cloned()
-                        .flatten()
-                        .unwrap_or_default()
-                } else if let Some(ctx) = step.parameters.get("context").and_then(|v| v.as_str()) {
-                    ctx.to_string()
-                } else {
-                    String::new()
-                };
-                // Create WebDriver instance for each agent (or share if needed)
-                let driver = WebDriver::new(
-                    "http://localhost:4444",
-                    thirtyfour::DesiredCapabilities::chrome(),
-                )
-                .await?;
-                let mut agent = crate::agent::agent::AIAgent::new(
-                    id,
-                    goal,
-                    description,
-                    tools,
-                    role,
-                    backstory,
-                    agent_context,
-                    driver,
-                );
-                // Run the agent
-                let result = agent.process().await;
-                match result {
-                    Ok(output) => {
-                        println!("Agent step {} output: {}", i, output);
-                        agent_outputs[i] = Some(output);
-                    }
-                    Err(e) => {
-                        eprintln!("Agent step {} failed: {}", i, e);
-                        agent_outputs[i] = None;
-                    }
+    let cli = Cli::parse();
+    let mut config = OculusConfig::load(cli.config.as_deref())?;
+    match &cli.command {
+        Command::Run { task, settings, .. } | Command::Plan { task, settings, .. } => {
+            settings.apply(&mut config);
+            cli.init_logging(&mut config)?;
+            plan_and_run(&cli, task.clone(), settings, config).await
+        }
+        Command::Report { trace, output } => {
+            cli.init_logging(&mut config)?;
+            let path = output.clone().unwrap_or_else(|| report_path(trace));
+            RunReport::load(trace)?.write_html(&path)?;
+            println!("{}", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// The `run` and `plan` subcommands.
+async fn plan_and_run(
+    cli: &Cli,
+    task: Option<String>,
+    settings: &Settings,
+    config: OculusConfig,
+) -> Result<(), Box<dyn Error>> {
+    set_provider(build_provider(&config.llm)?);
+
+    // A replayed session re-drives the recorded task with the recorded LLM responses.
+    let (replayed_task, replay) = match settings.replay_dir() {
+        Some(dir) => {
+            let archive = SessionArchive::load(&dir)?;
+            info!("Replaying session from {}", dir.display());
+            let replay = Arc::new(ReplayProvider::new(&archive));
+            set_provider(replay.clone());
+            (Some(archive.manifest.task), Some(replay))
+        }
+        None => (None, None),
+    };
+    let saved_plan = match &cli.command {
+        Command::Run {
+            plan: Some(path), ..
+        } => Some(path.as_path()),
+        _ => None,
+    };
+    let user_task = match (task.or(replayed_task), saved_plan) {
+        (Some(task), _) => task,
+        (None, Some(path)) => format!("Execute the saved plan {}", path.display()),
+        (None, None) => get_user_input("Enter the task you want to perform: "),
+    };
+    if user_task.trim().is_empty() {
+        return Err("No task given".into());
+    }
+
+    let recorder = match settings.record_dir() {
+        Some(dir) => {
+            let recorder = Arc::new(SessionRecorder::create(&dir, &user_task)?);
+            set_provider(Arc::new(RecordingProvider::new(
+                current_provider(),
+                recorder.clone(),
+            )));
+            info!("Recording session to {}", dir.display());
+            Some(recorder)
+        }
+        None => None,
+    };
+    info!(provider = current_provider().name(), "Using LLM provider");
+
+    let actions = Arc::new(ActionRegistry::with_builtins());
+    let plan = match saved_plan {
+        Some(path) => load_plan(path, &actions)?,
+        None => match planner_agent(&user_task, &actions.names(), MAX_PLAN_REPAIR_ATTEMPTS).await {
+            Ok(plan) => plan,
+            Err(e) => {
+                error!("Error generating planner AI response: {}", e);
+                if let Err(divergence) = check_replay(replay.as_deref()) {
+                    warn!("{}", divergence);
                 }
+                return Err(e);
             }
-            println!("All agent steps finished. Outputs: {:?}", agent_outputs);
+        },
+    };
+
+    if let Command::Plan { output, .. } = &cli.command {
+        let json = serde_json::to_string_pretty(&plan)?;
+        if let Some(path) = output {
+            std::fs::write(path, &json)?;
+            info!("Plan written to {}", path.display());
         }
-        Err(e) => {
-            eprintln!("Error generating planner AI response: {}", e);
+        println!("{}", json);
+        return check_replay(replay.as_deref());
+    }
+
+    info!("Planner AI plan: {:?}", plan);
+    let mut sessions = BrowserSessionManager::new(BrowserOptions {
+        backend: config.browser_backend,
+        webdriver_url: config.webdriver_url.clone(),
+        cdp_url: config.cdp_url.clone(),
+        headless: config.headless,
+        capabilities: config.browser.clone(),
+    })
+    .with_reuse(config.reuse_browser_sessions)
+    .with_max_idle(config.max_concurrency);
+    if let Some(file) = config
+        .profile
+        .file
+        .as_ref()
+        .filter(|f| !f.as_os_str().is_empty())
+    {
+        let profile = BrowserProfile::open(file, config.profile.domains.clone())
+            .map_err(|e| e.to_string())?;
+        info!("Using browser profile {}", file.display());
+        sessions = sessions.with_profile(Arc::new(profile), config.profile.save);
+    }
+    let sessions = Arc::new(sessions);
+    let mut executor = PlanExecutor::new(config.webdriver_url.clone())
+        .with_max_concurrency(config.max_concurrency)
+        .with_max_steps(config.max_steps)
+        .with_output_dir(config.output_dir.clone())
+        .with_sessions(sessions.clone())
+        .with_history_sharing(config.history_sharing)
+        .with_vision(config.vision.clone())
+        .with_observation(config.observation.clone())
+        .with_actions(actions.clone())
+        .with_replanning(Replanning {
+            user_task: user_task.clone(),
+            max_replans: config.max_replans,
+        });
+    if let Some(recorder) = &recorder {
+        executor = executor.with_recorder(recorder.clone());
+    }
+    if let Some(file) = config
+        .memory_file
+        .as_ref()
+        .filter(|f| !f.as_os_str().is_empty())
+    {
+        let mut memory = MemoryManager::open(file)
+            .map_err(|e| format!("Failed to open memory {}: {}", file.display(), e))?;
+        info!("Loaded {} memories from {}", memory.len(), file.display());
+        let embedder: Option<Arc<dyn Embedder>> = match config.memory_embedder {
+            EmbedderKind::Provider => Some(Arc::new(ProviderEmbedder::new(
+                current_provider(),
+                config.llm.embedding_model(),
+            ))),
+            EmbedderKind::Hash => Some(Arc::new(HashEmbedder::default())),
+            EmbedderKind::None => None,
+        };
+        if let Some(embedder) = embedder {
+            let index_path = file.with_extension("index.json");
+            let retriever = SemanticRetriever::open(embedder, &index_path).map_err(|e| {
+                format!(
+                    "Failed to open memory index {}: {}",
+                    index_path.display(),
+                    e
+                )
+            })?;
+            memory = memory.with_retriever(retriever);
+        }
+        executor = executor.with_memory(Arc::new(memory));
+    }
+    let trace = match config
+        .trace_dir
+        .as_ref()
+        .filter(|d| !d.as_os_str().is_empty())
+    {
+        Some(dir) => {
+            let trace = Arc::new(
+                TraceWriter::create(dir, &user_task)
+                    .map_err(|e| format!("Failed to create trace in {}: {}", dir.display(), e))?,
+            );
+            info!("Writing run trace to {}", trace.path().display());
+            executor = executor
+                .with_trace(trace.clone())
+                .with_screenshot_dir(trace.artifacts_dir());
+            Some(trace)
+        }
+        None => None,
+    };
+    // Dropping the run on Ctrl-C cancels the agents; the browsers they had open are
+    // still closed below.
+    let result = tokio::select! {
+        result = executor.execute(&plan) => result,
+        _ = tokio::signal::ctrl_c() => {
+            warn!("Interrupted, closing browser sessions");
+            Err("Interrupted".into())
+        }
+    };
+    sessions.shutdown().await;
+
+    if let Some(trace) = &trace {
+        let path = report_path(trace.path());
+        match RunReport::load(trace.path()).and_then(|report| report.write_html(&path)) {
+            Ok(()) => info!("Run report written to {}", path.display()),
+            Err(e) => warn!("Failed to write the run report: {}", e),
         }
     }
 
-    println!("Application finished.");
+    // A diverged replay is the more useful error, unless the run itself failed first
+    if let Err(e) = check_replay(replay.as_deref()) {
+        if result.is_ok() {
+            return Err(e);
+        }
+        warn!("{}", e);
+    }
+
+    let outcomes = result?;
+    let agent_outputs: Vec<Option<&str>> =
+        outcomes.iter().map(|outcome| outcome.output()).collect();
+    info!("All agent steps finished. Outputs: {:?}", agent_outputs);
+
+    let failed = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_err())
+        .count();
+    if failed > 0 {
+        return Err(format!("{} of {} plan step(s) failed", failed, outcomes.len()).into());
+    }
     Ok(())
 }
+
+/// Fails a replayed run whose LLM or embedding calls didn't all match the recording.
+fn check_replay(replay: Option<&ReplayProvider>) -> Result<(), Box<dyn Error>> {
+    let Some(replay) = replay else {
+        return Ok(());
+    };
+    let divergences = replay.divergences();
+    let unused = replay.unused();
+    if divergences.is_empty() && unused == 0 {
+        info!("Replay reproduced the recorded session");
+        return Ok(());
+    }
+    let mut message = String::from("Replay did not reproduce the recorded session");
+    for divergence in &divergences {
+        message.push_str("\n- ");
+        message.push_str(divergence);
+    }
+    if unused > 0 {
+        message.push_str(&format!("\n- {} recorded call(s) were never made", unused));
+    }
+    Err(message.into())
+}
+
+/// Reads a plan saved by `oculus plan` and checks it against the available actions.
+fn load_plan(path: &Path, actions: &ActionRegistry) -> Result<PlannerAgentPlan, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read plan {}: {}", path.display(), e))?;
+    let plan: PlannerAgentPlan = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid plan {}: {}", path.display(), e))?;
+    let issues = validate_plan(&plan, &actions.names());
+    if !issues.is_empty() {
+        return Err(format!(
+            "Invalid plan {}:\n- {}",
+            path.display(),
+            issues.join("\n- ")
+        )
+        .into());
+    }
+    Ok(plan)
+}
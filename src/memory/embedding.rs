@@ -0,0 +1,157 @@
+use crate::llm::provider::{LlmProvider, LlmResult};
+use crate::utils::fnv1a;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Turns memory contents and queries into vectors for semantic retrieval.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifies the embedding model. Vectors stored under a different id are never
+    /// compared with this embedder's vectors.
+    fn id(&self) -> String;
+
+    /// Embeds each text, in order.
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbedderKind {
+    /// The configured LLM provider's embedding endpoint.
+    #[default]
+    Provider,
+    /// [`HashEmbedder`]: local and deterministic, but only matches shared words.
+    Hash,
+    /// No embeddings; memories are ranked by keyword overlap.
+    None,
+}
+
+impl std::str::FromStr for EmbedderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "provider" | "llm" => Ok(EmbedderKind::Provider),
+            "hash" | "local" | "stub" => Ok(EmbedderKind::Hash),
+            "none" | "keyword" | "off" => Ok(EmbedderKind::None),
+            other => Err(format!("Unknown memory embedder: {}", other)),
+        }
+    }
+}
+
+/// Embeds through [`LlmProvider::embed`].
+pub struct ProviderEmbedder {
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+}
+
+impl ProviderEmbedder {
+    /// `model` should be the embedding model the provider is configured with; it only
+    /// names the vectors in the index.
+    pub fn new(provider: Arc<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        ProviderEmbedder {
+            provider,
+            model: model.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for ProviderEmbedder {
+    fn id(&self) -> String {
+        format!("{}:{}", self.provider.name(), self.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let vectors = self.provider.embed(texts).await?;
+        if vectors.len() != texts.len() {
+            return Err(
+                format!("Expected {} embeddings, got {}", texts.len(), vectors.len()).into(),
+            );
+        }
+        Ok(vectors)
+    }
+}
+
+/// Deterministic local embedder that hashes words and character trigrams into a fixed
+/// number of buckets. Needs no network, so it suits offline runs and tests.
+pub struct HashEmbedder {
+    dims: usize,
+}
+
+impl HashEmbedder {
+    pub const DEFAULT_DIMS: usize = 256;
+
+    pub fn new(dims: usize) -> Self {
+        HashEmbedder { dims: dims.max(1) }
+    }
+
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dims];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            let word = word.to_lowercase();
+            self.add_feature(&mut vector, word.as_bytes(), 1.0);
+
+            let padded: Vec<char> = format!(" {} ", word).chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                self.add_feature(&mut vector, trigram.as_bytes(), 0.5);
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+
+    fn add_feature(&self, vector: &mut [f32], feature: &[u8], weight: f32) {
+        let hash = fnv1a(feature);
+        let bucket = (hash % self.dims as u64) as usize;
+        // The top bit picks the sign so colliding features tend to cancel out.
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        HashEmbedder::new(HashEmbedder::DEFAULT_DIMS)
+    }
+}
+
+#[async_trait]
+impl Embedder for HashEmbedder {
+    fn id(&self) -> String {
+        format!("hash:{}", self.dims)
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+/// Cosine similarity, or 0 if either vector is zero or they differ in length.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for x in vector.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
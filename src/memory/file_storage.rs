@@ -0,0 +1,80 @@
+use crate::memory::types::MemoryRecord;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Where memory records are persisted.
+pub trait MemoryStorage: Send + Sync {
+    /// Reads every stored record, oldest first.
+    fn load(&self) -> io::Result<Vec<MemoryRecord>>;
+
+    fn append(&self, record: &MemoryRecord) -> io::Result<()>;
+}
+
+/// Stores records as an append-only JSON Lines file.
+pub struct FileMemoryStorage {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileMemoryStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileMemoryStorage {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl MemoryStorage for FileMemoryStorage {
+    /// A missing file is an empty memory. Lines that don't parse are skipped with a
+    /// warning so one bad write doesn't lose the rest.
+    fn load(&self) -> io::Result<Vec<MemoryRecord>> {
+        let _guard = self.lock.lock().unwrap();
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!(
+                    "Skipping invalid memory record at {}:{}: {}",
+                    self.path.display(),
+                    number + 1,
+                    e
+                ),
+            }
+        }
+        Ok(records)
+    }
+
+    fn append(&self, record: &MemoryRecord) -> io::Result<()> {
+        let line = serde_json::to_string(record)?;
+        let _guard = self.lock.lock().unwrap();
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        file.flush()
+    }
+}
@@ -0,0 +1,124 @@
+use crate::memory::embedding::cosine_similarity;
+use crate::memory::types::MemoryRecord;
+use crate::utils::fnv1a;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Hash of the embedded text, so edited memories are re-embedded.
+    content_hash: u64,
+    vector: Vec<f32>,
+}
+
+/// Embedding vectors of memory records, keyed by record id and saved as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingIndex {
+    /// [`Embedder::id`](crate::memory::embedding::Embedder::id) of the vectors.
+    embedder: String,
+    entries: BTreeMap<u64, IndexEntry>,
+}
+
+impl EmbeddingIndex {
+    pub fn new(embedder: impl Into<String>) -> Self {
+        EmbeddingIndex {
+            embedder: embedder.into(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the index at `path`. A missing file, or one built by a different embedder,
+    /// gives an empty index.
+    pub fn load(path: &Path, embedder: &str) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(EmbeddingIndex::new(embedder));
+            }
+            Err(e) => return Err(e),
+        };
+        let index: EmbeddingIndex = serde_json::from_str(&contents)?;
+        if index.embedder != embedder {
+            info!(
+                "Memory index {} was built with {}; rebuilding it with {}",
+                path.display(),
+                index.embedder,
+                embedder
+            );
+            return Ok(EmbeddingIndex::new(embedder));
+        }
+        Ok(index)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename so an interrupted save never leaves a truncated index.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records that have no vector yet, or whose text changed since it was embedded.
+    pub fn missing<'a>(&self, records: &'a [MemoryRecord]) -> Vec<&'a MemoryRecord> {
+        records
+            .iter()
+            .filter(|record| {
+                self.entries.get(&record.id).map(|entry| entry.content_hash)
+                    != Some(content_hash(record))
+            })
+            .collect()
+    }
+
+    pub fn insert(&mut self, record: &MemoryRecord, vector: Vec<f32>) {
+        self.entries.insert(
+            record.id,
+            IndexEntry {
+                content_hash: content_hash(record),
+                vector,
+            },
+        );
+    }
+
+    /// Drops vectors of records that no longer exist. Returns whether anything was removed.
+    pub fn retain(&mut self, records: &[MemoryRecord]) -> bool {
+        let ids: HashSet<u64> = records.iter().map(|record| record.id).collect();
+        let before = self.entries.len();
+        self.entries.retain(|id, _| ids.contains(id));
+        self.entries.len() != before
+    }
+
+    /// Cosine similarity between `query` and the vector of record `id`, if indexed.
+    pub fn similarity(&self, id: u64, query: &[f32]) -> Option<f32> {
+        self.entries
+            .get(&id)
+            .map(|entry| cosine_similarity(&entry.vector, query))
+    }
+}
+
+/// The text embedded for a record: its content plus the site it is about.
+pub fn embedding_text(record: &MemoryRecord) -> String {
+    match &record.domain {
+        Some(domain) => format!("{} ({})", record.content, domain),
+        None => record.content.clone(),
+    }
+}
+
+fn content_hash(record: &MemoryRecord) -> u64 {
+    fnv1a(embedding_text(record).as_bytes())
+}
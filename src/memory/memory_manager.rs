@@ -0,0 +1,210 @@
+use crate::memory::file_storage::{FileMemoryStorage, MemoryStorage};
+use crate::memory::retrieval::SemanticRetriever;
+use crate::memory::types::{MemoryKind, MemoryQuery, MemoryRecord, domain_of};
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Long-term memory shared by the agents of a run and persisted across runs.
+///
+/// Records are kept in memory for lookups and appended to the storage as they are added.
+pub struct MemoryManager {
+    storage: Box<dyn MemoryStorage>,
+    records: RwLock<Vec<MemoryRecord>>,
+    retriever: Option<SemanticRetriever>,
+    /// Set after the first retrieval error so the rest of the run uses keyword matching.
+    semantic_failed: AtomicBool,
+}
+
+impl MemoryManager {
+    /// Opens (or starts) a memory file.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        MemoryManager::new(Box::new(FileMemoryStorage::new(path)))
+    }
+
+    pub fn new(storage: Box<dyn MemoryStorage>) -> io::Result<Self> {
+        let records = storage.load()?;
+        Ok(MemoryManager {
+            storage,
+            records: RwLock::new(records),
+            retriever: None,
+            semantic_failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Ranks memories by embedding similarity in [`MemoryManager::retrieve`] instead of
+    /// keyword overlap.
+    pub fn with_retriever(mut self, retriever: SemanticRetriever) -> Self {
+        self.retriever = Some(retriever);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn records(&self) -> Vec<MemoryRecord> {
+        self.records.read().unwrap().clone()
+    }
+
+    /// Stores a memory unless an equivalent one exists: same kind and domain with the same
+    /// content, or for visited pages the same URL. Returns the new record, if any.
+    ///
+    /// Storage failures are logged rather than returned; the record stays available for
+    /// the rest of the run.
+    pub fn remember(
+        &self,
+        kind: MemoryKind,
+        content: &str,
+        url: Option<&str>,
+        agent_id: &str,
+    ) -> Option<MemoryRecord> {
+        let content = content.trim();
+        if content.is_empty() {
+            return None;
+        }
+        let domain = url.and_then(domain_of);
+
+        let mut records = self.records.write().unwrap();
+        let duplicate = records.iter().any(|existing| {
+            existing.kind == kind
+                && existing.domain == domain
+                && match kind {
+                    MemoryKind::VisitedPage => existing.url.as_deref() == url,
+                    _ => normalize(&existing.content) == normalize(content),
+                }
+        });
+        if duplicate {
+            return None;
+        }
+
+        let record = MemoryRecord {
+            id: records.iter().map(|r| r.id + 1).max().unwrap_or(0),
+            kind,
+            content: content.to_string(),
+            url: url.map(str::to_string),
+            domain,
+            agent_id: agent_id.to_string(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        if let Err(e) = self.storage.append(&record) {
+            warn!("Failed to persist memory: {}", e);
+        }
+        records.push(record.clone());
+        Some(record)
+    }
+
+    /// Returns up to `query.limit` memories relevant to the query, best first.
+    ///
+    /// Memories about the query's domain score highest, then those sharing words with
+    /// `query.text`; facts and failures outrank pages and findings, and ties go to the
+    /// newest. Memories matching neither the domain nor the text are left out.
+    pub fn recall(&self, query: &MemoryQuery) -> Vec<MemoryRecord> {
+        let domain = query.url.as_deref().and_then(domain_of);
+        let terms = terms(&query.text);
+        let records = self.records.read().unwrap();
+
+        let mut scored: Vec<(usize, &MemoryRecord)> = records
+            .iter()
+            .filter_map(|record| {
+                let same_domain = domain.is_some() && record.domain == domain;
+                let overlap = terms.intersection(&terms_of(record)).count();
+                if !same_domain && overlap == 0 {
+                    return None;
+                }
+                let kind_weight = match record.kind {
+                    MemoryKind::Fact | MemoryKind::Failure => 2,
+                    MemoryKind::Finding | MemoryKind::VisitedPage => 1,
+                };
+                Some((
+                    usize::from(same_domain) * 10 + overlap * 2 + kind_weight,
+                    record,
+                ))
+            })
+            .collect();
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| b.created_at.cmp(&a.created_at))
+                .then_with(|| b.id.cmp(&a.id))
+        });
+        scored
+            .into_iter()
+            .take(query.limit)
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
+    /// Returns up to `query.limit` memories relevant to the query, using the semantic
+    /// retriever when one is set and falling back to [`MemoryManager::recall`] if it fails.
+    pub async fn retrieve(&self, query: &MemoryQuery) -> Vec<MemoryRecord> {
+        let Some(retriever) = &self.retriever else {
+            return self.recall(query);
+        };
+        if self.semantic_failed.load(Ordering::Relaxed) {
+            return self.recall(query);
+        }
+
+        let records = self.records();
+        match retriever.retrieve(&records, query).await {
+            Ok(memories) => memories,
+            Err(e) => {
+                warn!(
+                    "Semantic memory retrieval with {} failed: {}. Falling back to keyword matching.",
+                    retriever.embedder_id(),
+                    e
+                );
+                self.semantic_failed.store(true, Ordering::Relaxed);
+                self.recall(query)
+            }
+        }
+    }
+
+    /// Formats memories as a bullet list for the task prompt.
+    pub fn render(records: &[MemoryRecord]) -> String {
+        records
+            .iter()
+            .map(|record| match &record.domain {
+                Some(domain) => {
+                    format!("- [{}, {}] {}", record.kind.label(), domain, record.content)
+                }
+                None => format!("- [{}] {}", record.kind.label(), record.content),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Lowercased words of four or more characters, which skips most stop words.
+fn terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn terms_of(record: &MemoryRecord) -> HashSet<String> {
+    let mut terms = terms(&record.content);
+    if let Some(domain) = &record.domain {
+        terms.extend(self::terms(domain));
+    }
+    terms
+}
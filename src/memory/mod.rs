@@ -0,0 +1,6 @@
+pub mod embedding;
+pub mod file_storage;
+pub mod index;
+pub mod memory_manager;
+pub mod retrieval;
+pub mod types;
@@ -0,0 +1,117 @@
+use crate::llm::provider::LlmResult;
+use crate::memory::embedding::Embedder;
+use crate::memory::index::{EmbeddingIndex, embedding_text};
+use crate::memory::types::{MemoryQuery, MemoryRecord, domain_of};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Added to the similarity of memories about the site the agent is on.
+const SAME_DOMAIN_BONUS: f32 = 0.2;
+
+/// Memories less similar than this to the query are left out unless they are about the
+/// site the agent is on, like keyword recall leaves out memories that share no words.
+const MIN_SIMILARITY: f32 = 0.2;
+
+/// Texts embedded per request when indexing new memories.
+const EMBED_BATCH_SIZE: usize = 32;
+
+/// Ranks memories by embedding similarity to the query, keeping the vectors in an
+/// on-disk [`EmbeddingIndex`] so each memory is embedded once.
+pub struct SemanticRetriever {
+    embedder: Arc<dyn Embedder>,
+    index: Mutex<EmbeddingIndex>,
+    index_path: PathBuf,
+}
+
+impl SemanticRetriever {
+    pub fn open(embedder: Arc<dyn Embedder>, index_path: impl Into<PathBuf>) -> io::Result<Self> {
+        let index_path = index_path.into();
+        let index = EmbeddingIndex::load(&index_path, &embedder.id())?;
+        Ok(SemanticRetriever {
+            embedder,
+            index: Mutex::new(index),
+            index_path,
+        })
+    }
+
+    pub fn embedder_id(&self) -> String {
+        self.embedder.id()
+    }
+
+    /// Embeds records missing from the index and drops vectors of removed records, then
+    /// saves the index if it changed.
+    pub async fn sync(&self, records: &[MemoryRecord]) -> LlmResult<()> {
+        let missing: Vec<MemoryRecord> = {
+            let index = self.index.lock().unwrap();
+            index.missing(records).into_iter().cloned().collect()
+        };
+
+        let mut changed = false;
+        for batch in missing.chunks(EMBED_BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(embedding_text).collect();
+            let vectors = self.embedder.embed(&texts).await?;
+            let mut index = self.index.lock().unwrap();
+            for (record, vector) in batch.iter().zip(vectors) {
+                index.insert(record, vector);
+            }
+            changed = true;
+        }
+
+        let mut index = self.index.lock().unwrap();
+        changed |= index.retain(records);
+        if changed {
+            index.save(&self.index_path)?;
+        }
+        Ok(())
+    }
+
+    /// Returns up to `query.limit` records most similar to the query's text and URL,
+    /// leaving out dissimilar records that aren't about the query's site.
+    pub async fn retrieve(
+        &self,
+        records: &[MemoryRecord],
+        query: &MemoryQuery,
+    ) -> LlmResult<Vec<MemoryRecord>> {
+        if records.is_empty() || query.limit == 0 {
+            return Ok(Vec::new());
+        }
+        self.sync(records).await?;
+
+        let query_text = match &query.url {
+            Some(url) => format!("{}\n{}", query.text, url),
+            None => query.text.clone(),
+        };
+        let query_vector = self
+            .embedder
+            .embed(&[query_text])
+            .await?
+            .pop()
+            .ok_or("Embedder returned no vector for the query")?;
+        let domain = query.url.as_deref().and_then(domain_of);
+
+        let index = self.index.lock().unwrap();
+        let mut scored: Vec<(f32, &MemoryRecord)> = records
+            .iter()
+            .filter_map(|record| {
+                let similarity = index.similarity(record.id, &query_vector)?;
+                let same_domain = domain.is_some() && record.domain == domain;
+                if similarity < MIN_SIMILARITY && !same_domain {
+                    return None;
+                }
+                let bonus = if same_domain { SAME_DOMAIN_BONUS } else { 0.0 };
+                Some((similarity + bonus, record))
+            })
+            .collect();
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .total_cmp(a_score)
+                .then_with(|| b.created_at.cmp(&a.created_at))
+        });
+        Ok(scored
+            .into_iter()
+            .take(query.limit)
+            .map(|(_, record)| record.clone())
+            .collect())
+    }
+}
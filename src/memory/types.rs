@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryKind {
+    /// Durable knowledge about a site, e.g. "example.com needs the cookie banner accepted".
+    Fact,
+    /// A page whose content an agent extracted.
+    VisitedPage,
+    /// A step's final answer.
+    Finding,
+    /// An action or step that failed, so later runs can avoid repeating it.
+    Failure,
+}
+
+impl MemoryKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MemoryKind::Fact => "fact",
+            MemoryKind::VisitedPage => "visited page",
+            MemoryKind::Finding => "finding",
+            MemoryKind::Failure => "failure",
+        }
+    }
+}
+
+/// One entry in long-term memory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryRecord {
+    pub id: u64,
+    pub kind: MemoryKind,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Host of `url` without `www.`, used to match memories to the current site.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Id of the agent that stored the memory.
+    pub agent_id: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+/// What to look up in [`MemoryManager::recall`](crate::memory::memory_manager::MemoryManager::recall).
+#[derive(Debug, Clone, Default)]
+pub struct MemoryQuery {
+    /// The page the agent is on; memories about the same domain rank first.
+    pub url: Option<String>,
+    /// Free text, typically the agent's goal, matched against memory contents.
+    pub text: String,
+    pub limit: usize,
+}
+
+/// Extracts the host from a URL, lowercased and without a leading `www.`.
+pub fn domain_of(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_string())
+    }
+}
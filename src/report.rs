@@ -0,0 +1,443 @@
+use crate::agent::planner::PlannerAgentPlan;
+use crate::trace::{AgentStepTrace, TraceEvent, TraceRecord};
+use base64::Engine;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A run rebuilt from its JSONL trace, rendered as a single self-contained HTML file.
+#[derive(Debug, Clone, Default)]
+pub struct RunReport {
+    pub run_id: String,
+    pub task: String,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// `(duration_ms, failed steps)`, if the run finished.
+    pub finished: Option<(u64, usize)>,
+    /// Every plan revision, oldest first.
+    pub plans: Vec<Value>,
+    /// Plan steps in the order they started.
+    pub steps: Vec<StepReport>,
+    /// Relative screenshot paths that don't exist from the working directory are looked
+    /// up here.
+    pub base_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StepReport {
+    pub index: usize,
+    pub id: String,
+    pub started_at: u64,
+    pub duration_ms: Option<u64>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub agent_steps: Vec<AgentStepTrace>,
+}
+
+impl RunReport {
+    /// Reads a trace written by [`crate::trace::TraceWriter`]. Lines that don't parse are
+    /// skipped, so the trace of an interrupted run still gives a report.
+    pub fn load(trace_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(trace_path)
+            .map_err(|e| format!("Failed to read trace {}: {}", trace_path.display(), e))?;
+        let records: Vec<TraceRecord> = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        if records.is_empty() {
+            return Err(format!("Trace {} has no events", trace_path.display()).into());
+        }
+        let base_dir = trace_path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(RunReport::from_records(records, base_dir))
+    }
+
+    pub fn from_records(records: Vec<TraceRecord>, base_dir: PathBuf) -> Self {
+        let mut report = RunReport {
+            base_dir,
+            ..Default::default()
+        };
+        for record in records {
+            if report.run_id.is_empty() {
+                report.run_id = record.run_id.clone();
+                report.started_at = record.ts;
+            }
+            match record.event {
+                TraceEvent::RunStarted { task } => {
+                    report.task = task;
+                    report.started_at = record.ts;
+                }
+                TraceEvent::Plan { plan, .. } => report.plans.push(plan),
+                TraceEvent::PlanStepStarted { index, id } => report.steps.push(StepReport {
+                    index,
+                    id,
+                    started_at: record.ts,
+                    ..Default::default()
+                }),
+                TraceEvent::PlanStepFinished {
+                    id,
+                    duration_ms,
+                    output,
+                    error,
+                    ..
+                } => {
+                    let step = report.step_mut(&id, record.ts);
+                    step.duration_ms = Some(duration_ms);
+                    step.output = output;
+                    step.error = error;
+                }
+                TraceEvent::AgentStep(step) => report
+                    .step_mut(&step.agent_id, step.started_at)
+                    .agent_steps
+                    .push(*step),
+                TraceEvent::RunFinished {
+                    duration_ms,
+                    failed,
+                    ..
+                } => report.finished = Some((duration_ms, failed)),
+            }
+        }
+        report
+    }
+
+    /// The latest plan step with `id`, added if the trace has none (e.g. an agent run
+    /// outside a plan).
+    fn step_mut(&mut self, id: &str, ts: u64) -> &mut StepReport {
+        match self.steps.iter().rposition(|step| step.id == id) {
+            Some(position) => &mut self.steps[position],
+            None => {
+                self.steps.push(StepReport {
+                    index: self.steps.len(),
+                    id: id.to_string(),
+                    started_at: ts,
+                    ..Default::default()
+                });
+                self.steps.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn write_html(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_html())
+            .map_err(|e| format!("Failed to write report {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let status = match self.finished {
+            Some((_, 0)) => ("ok", "Finished"),
+            Some(_) => ("failed", "Finished with failures"),
+            None => ("failed", "Did not finish"),
+        };
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Oculus run {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+            escape(&self.run_id),
+            STYLE
+        );
+        let _ = write!(
+            html,
+            "<header><h1>{}</h1><p class=\"meta\">Run {} &middot; <span class=\"badge {}\">{}</span>",
+            escape(&self.task),
+            escape(&self.run_id),
+            status.0,
+            status.1
+        );
+        if let Some((duration_ms, failed)) = self.finished {
+            let _ = write!(
+                html,
+                " &middot; {} &middot; {} of {} step(s) failed",
+                format_duration(duration_ms),
+                failed,
+                self.steps.len()
+            );
+        }
+        html.push_str("</p></header>\n");
+
+        self.render_outputs(&mut html);
+        self.render_plan(&mut html);
+        self.render_timeline(&mut html);
+        for step in &self.steps {
+            self.render_step(&mut html, step);
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn render_outputs(&self, html: &mut String) {
+        html.push_str("<section><h2>Final outputs</h2>\n");
+        for step in &self.steps {
+            let _ = write!(html, "<div class=\"output\"><h3>{}</h3>", escape(&step.id));
+            match (&step.output, &step.error) {
+                (Some(output), _) => {
+                    let _ = write!(html, "<pre>{}</pre>", escape(output));
+                }
+                (None, Some(error)) => {
+                    let _ = write!(html, "<pre class=\"error\">{}</pre>", escape(error));
+                }
+                (None, None) => html.push_str("<p class=\"meta\">No output recorded.</p>"),
+            }
+            html.push_str("</div>\n");
+        }
+        html.push_str("</section>\n");
+    }
+
+    fn render_plan(&self, html: &mut String) {
+        let Some(latest) = self.plans.last() else {
+            return;
+        };
+        html.push_str("<section><h2>Plan</h2>\n");
+        if self.plans.len() > 1 {
+            let _ = write!(
+                html,
+                "<p class=\"meta\">Revised {} time(s) during the run.</p>",
+                self.plans.len() - 1
+            );
+        }
+        match serde_json::from_value::<PlannerAgentPlan>(latest.clone()) {
+            Ok(plan) => {
+                html.push_str("<ol start=\"0\">\n");
+                for (index, step) in plan.steps.iter().enumerate() {
+                    let needs: Vec<String> = plan
+                        .context_sources(index)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|source| plan.steps[source].id.clone())
+                        .collect();
+                    let _ = write!(
+                        html,
+                        "<li><strong>{}</strong>: {}",
+                        escape(&step.id),
+                        escape(&step.param("goal"))
+                    );
+                    if !needs.is_empty() {
+                        let _ = write!(
+                            html,
+                            " <span class=\"meta\">(uses {})</span>",
+                            escape(&needs.join(", "))
+                        );
+                    }
+                    html.push_str("</li>\n");
+                }
+                html.push_str("</ol>\n");
+            }
+            Err(_) => {
+                let _ = write!(html, "<pre>{}</pre>", escape(&pretty(latest)));
+            }
+        }
+        for (revision, plan) in self.plans.iter().enumerate() {
+            let _ = writeln!(
+                html,
+                "<details><summary>Revision {} (JSON)</summary><pre>{}</pre></details>",
+                revision,
+                escape(&pretty(plan))
+            );
+        }
+        html.push_str("</section>\n");
+    }
+
+    /// One bar per plan step, positioned by start time and duration.
+    fn render_timeline(&self, html: &mut String) {
+        let end = self
+            .steps
+            .iter()
+            .map(|step| step.started_at + step.duration_ms.unwrap_or(0))
+            .max()
+            .unwrap_or(self.started_at);
+        let total = end.saturating_sub(self.started_at).max(1) as f64;
+        html.push_str("<section><h2>Timeline</h2>\n<div class=\"timeline\">\n");
+        for step in &self.steps {
+            let offset = step.started_at.saturating_sub(self.started_at) as f64 / total * 100.0;
+            let width = (step.duration_ms.unwrap_or(0) as f64 / total * 100.0).max(0.5);
+            let class = if step.error.is_some() { "failed" } else { "ok" };
+            let _ = writeln!(
+                html,
+                "<div class=\"row\"><span class=\"label\"><a href=\"#step-{}\">{}</a></span>\
+                 <span class=\"track\"><span class=\"bar {}\" style=\"left:{:.2}%;width:{:.2}%\" \
+                 title=\"{}\"></span></span></div>",
+                step.index,
+                escape(&step.id),
+                class,
+                offset,
+                width.min(100.0 - offset),
+                step.duration_ms
+                    .map(format_duration)
+                    .unwrap_or_else(|| "running".to_string())
+            );
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+
+    fn render_step(&self, html: &mut String, step: &StepReport) {
+        let _ = writeln!(
+            html,
+            "<section id=\"step-{}\"><h2>Step {}: {}</h2><p class=\"meta\">{}</p>",
+            step.index,
+            step.index,
+            escape(&step.id),
+            step.duration_ms
+                .map(format_duration)
+                .unwrap_or_else(|| "Did not finish".to_string())
+        );
+        for agent_step in &step.agent_steps {
+            self.render_agent_step(html, agent_step);
+        }
+        html.push_str("</section>\n");
+    }
+
+    fn render_agent_step(&self, html: &mut String, step: &AgentStepTrace) {
+        let class = if step.error.is_some() { "failed" } else { "ok" };
+        let _ = write!(
+            html,
+            "<article class=\"agent-step {}\"><h3>Agent step {}</h3><dl>",
+            class, step.step
+        );
+        let _ = write!(html, "<dt>Goal</dt><dd>{}</dd>", escape(&step.goal));
+        let _ = write!(html, "<dt>Page</dt><dd>{}", link(&step.url_before));
+        if let Some(after) = &step.url_after
+            && after != &step.url_before
+        {
+            let _ = write!(html, " &rarr; {}", link(after));
+        }
+        html.push_str("</dd>");
+        match (&step.action, &step.action_error) {
+            (Some(action), _) => {
+                let _ = write!(
+                    html,
+                    "<dt>Action</dt><dd><code>{}</code></dd>",
+                    escape(&action.to_string())
+                );
+            }
+            (None, Some(error)) => {
+                let _ = write!(
+                    html,
+                    "<dt>Action</dt><dd class=\"error\">Rejected: {}</dd>",
+                    escape(error)
+                );
+            }
+            (None, None) => {}
+        }
+        if let Some(result) = &step.result {
+            let _ = write!(html, "<dt>Result</dt><dd>{}</dd>", escape(result));
+        }
+        if let Some(error) = &step.error {
+            let _ = write!(
+                html,
+                "<dt>Error</dt><dd class=\"error\">{}</dd>",
+                escape(error)
+            );
+        }
+        let mut llm = format!("{} prompt chars", step.prompt_chars);
+        if let Some(latency) = step.llm_latency_ms {
+            llm.push_str(&format!(", {}", format_duration(latency)));
+        }
+        if let Some(total) = step.usage.as_ref().and_then(|usage| usage.total_tokens) {
+            llm.push_str(&format!(", {} tokens", total));
+        }
+        let _ = write!(html, "<dt>LLM</dt><dd>{}</dd></dl>", escape(&llm));
+        if let Some(content) = &step.extracted {
+            let _ = write!(
+                html,
+                "<details><summary>Extracted content</summary><pre>{}</pre></details>",
+                escape(content)
+            );
+        }
+        if let Some(path) = &step.screenshot {
+            match self.screenshot_data(path) {
+                Some(data) => {
+                    let _ = write!(
+                        html,
+                        "<details open><summary>Screenshot</summary>\
+                         <img src=\"data:image/png;base64,{}\" alt=\"Screenshot of step {}\"></details>",
+                        data, step.step
+                    );
+                }
+                None => {
+                    let _ = write!(
+                        html,
+                        "<p class=\"meta\">Screenshot {} is missing.</p>",
+                        escape(path)
+                    );
+                }
+            }
+        }
+        html.push_str("</article>\n");
+    }
+
+    /// The screenshot at `path` as base64, so the report needs no other files.
+    fn screenshot_data(&self, path: &str) -> Option<String> {
+        let path = Path::new(path);
+        let bytes = fs::read(path)
+            .or_else(|_| fs::read(self.base_dir.join(path)))
+            .ok()?;
+        Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+}
+
+/// The report path for a trace: the same file name with an `.html` extension.
+pub fn report_path(trace_path: &Path) -> PathBuf {
+    trace_path.with_extension("html")
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn link(url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        format!("<a href=\"{0}\">{0}</a>", escape(url))
+    } else {
+        escape(url)
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+fn format_duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m {}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+const STYLE: &str = "\
+body{font-family:system-ui,sans-serif;max-width:1100px;margin:0 auto;padding:1.5rem;color:#1f2328;background:#f6f8fa}\
+section,header{background:#fff;border:1px solid #d0d7de;border-radius:8px;padding:1rem 1.25rem;margin-bottom:1rem}\
+h1{font-size:1.4rem;margin:0 0 .25rem}h2{font-size:1.15rem;margin-top:0}h3{font-size:1rem;margin:.25rem 0}\
+.meta{color:#57606a;font-size:.9rem}\
+.badge{padding:.1rem .5rem;border-radius:1rem;color:#fff}.badge.ok{background:#1a7f37}.badge.failed{background:#cf222e}\
+pre{white-space:pre-wrap;word-break:break-word;background:#f6f8fa;padding:.75rem;border-radius:6px;max-height:30rem;overflow:auto}\
+.error{color:#cf222e}\
+.timeline .row{display:flex;align-items:center;margin:.2rem 0}\
+.timeline .label{width:12rem;overflow:hidden;text-overflow:ellipsis;white-space:nowrap}\
+.timeline .track{position:relative;flex:1;height:1rem;background:#eaeef2;border-radius:4px}\
+.timeline .bar{position:absolute;top:0;bottom:0;border-radius:4px}\
+.bar.ok{background:#2da44e}.bar.failed{background:#cf222e}\
+.agent-step{border-left:4px solid #2da44e;padding:.25rem .75rem;margin:.75rem 0}\
+.agent-step.failed{border-left-color:#cf222e}\
+dl{display:grid;grid-template-columns:6rem 1fr;gap:.25rem .75rem;margin:.5rem 0}dt{font-weight:600}dd{margin:0;word-break:break-word}\
+img{max-width:100%;border:1px solid #d0d7de;border-radius:6px;margin-top:.5rem}";
@@ -0,0 +1,166 @@
+use crate::llm::image::ImagePart;
+use crate::llm::provider::{LlmProvider, LlmResponse, LlmResult};
+use crate::session::types::{
+    EVENTS_FILE, EmbeddingExchange, LlmExchange, MANIFEST_FILE, SCREENSHOTS_DIR, SessionEvent,
+    SessionManifest,
+};
+use async_trait::async_trait;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Writes a session archive: `manifest.json`, an append-only `events.jsonl` and a
+/// `screenshots/` directory.
+pub struct SessionRecorder {
+    dir: PathBuf,
+    events: Mutex<File>,
+    llm_calls: AtomicUsize,
+}
+
+impl SessionRecorder {
+    /// Creates (or truncates) the archive in `dir` for a session started with `task`.
+    pub fn create(dir: impl AsRef<Path>, task: &str) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(SCREENSHOTS_DIR))?;
+
+        let manifest = SessionManifest {
+            version: 1,
+            task: task.to_string(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+
+        let events = File::create(dir.join(EVENTS_FILE))?;
+        Ok(SessionRecorder {
+            dir,
+            events: Mutex::new(events),
+            llm_calls: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Appends an event to `events.jsonl`. Failures are logged rather than returned so a
+    /// broken archive never aborts the run being recorded.
+    pub fn record(&self, event: &SessionEvent) {
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize session event: {}", e);
+                return;
+            }
+        };
+        let mut file = self.events.lock().unwrap();
+        if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+            warn!("Failed to write session event: {}", e);
+        }
+    }
+
+    /// Copies a screenshot into the archive and records a [`SessionEvent::Screenshot`].
+    pub fn record_screenshot(&self, agent_id: &str, step: usize, source: &Path) {
+        let relative = format!("{}/{}_step_{}.png", SCREENSHOTS_DIR, agent_id, step);
+        if let Err(e) = fs::copy(source, self.dir.join(&relative)) {
+            warn!("Failed to copy screenshot into session archive: {}", e);
+            return;
+        }
+        self.record(&SessionEvent::Screenshot {
+            agent_id: agent_id.to_string(),
+            step,
+            path: relative,
+        });
+    }
+
+    fn next_llm_seq(&self) -> usize {
+        self.llm_calls.fetch_add(1, Ordering::SeqCst)
+    }
+}
+
+/// Wraps another provider and records every exchange into a [`SessionRecorder`].
+pub struct RecordingProvider {
+    inner: Arc<dyn LlmProvider>,
+    recorder: Arc<SessionRecorder>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, recorder: Arc<SessionRecorder>) -> Self {
+        RecordingProvider { inner, recorder }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RecordingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String> {
+        Ok(self
+            .generate_with_usage(prompt, system_instructions)
+            .await?
+            .text)
+    }
+
+    async fn generate_with_usage(
+        &self,
+        prompt: &str,
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        self.generate_with_images(prompt, &[], system_instructions)
+            .await
+    }
+
+    /// Images are passed through; the screenshots they come from are archived separately.
+    async fn generate_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImagePart],
+        system_instructions: &str,
+    ) -> LlmResult<LlmResponse> {
+        let seq = self.recorder.next_llm_seq();
+        let result = self
+            .inner
+            .generate_with_images(prompt, images, system_instructions)
+            .await;
+        let (response, error) = match &result {
+            Ok(response) => (Some(response.text.clone()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.recorder.record(&SessionEvent::Llm(LlmExchange {
+            seq,
+            prompt: prompt.to_string(),
+            system_instructions: system_instructions.to_string(),
+            response,
+            error,
+        }));
+        result
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let seq = self.recorder.next_llm_seq();
+        let result = self.inner.embed(texts).await;
+        let (vectors, error) = match &result {
+            Ok(vectors) => (Some(vectors.clone()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.recorder
+            .record(&SessionEvent::Embedding(EmbeddingExchange {
+                seq,
+                texts: texts.to_vec(),
+                vectors,
+                error,
+            }));
+        result
+    }
+}
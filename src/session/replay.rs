@@ -0,0 +1,188 @@
+use crate::llm::provider::{LlmProvider, LlmResult};
+use crate::session::types::{
+    EVENTS_FILE, EmbeddingExchange, LlmExchange, MANIFEST_FILE, SessionEvent, SessionManifest,
+};
+use async_trait::async_trait;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// A session archive loaded back from disk.
+#[derive(Debug, Clone)]
+pub struct SessionArchive {
+    pub dir: PathBuf,
+    pub manifest: SessionManifest,
+    pub events: Vec<SessionEvent>,
+}
+
+impl SessionArchive {
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let dir = dir.as_ref().to_path_buf();
+        let manifest: SessionManifest =
+            serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE))?)?;
+
+        let raw_events = fs::read_to_string(dir.join(EVENTS_FILE))?;
+        let mut events = Vec::new();
+        for (line_no, line) in raw_events.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: SessionEvent = serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {}", EVENTS_FILE, line_no + 1, e))?;
+            events.push(event);
+        }
+
+        Ok(SessionArchive {
+            dir,
+            manifest,
+            events,
+        })
+    }
+
+    /// Recorded LLM exchanges in call order.
+    pub fn llm_exchanges(&self) -> Vec<LlmExchange> {
+        let mut exchanges: Vec<LlmExchange> = self
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                SessionEvent::Llm(exchange) => Some(exchange.clone()),
+                _ => None,
+            })
+            .collect();
+        exchanges.sort_by_key(|exchange| exchange.seq);
+        exchanges
+    }
+
+    /// Recorded embedding calls in call order.
+    pub fn embedding_exchanges(&self) -> Vec<EmbeddingExchange> {
+        let mut exchanges: Vec<EmbeddingExchange> = self
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                SessionEvent::Embedding(exchange) => Some(exchange.clone()),
+                _ => None,
+            })
+            .collect();
+        exchanges.sort_by_key(|exchange| exchange.seq);
+        exchanges
+    }
+
+    /// Events belonging to one agent (observations, actions and screenshots).
+    pub fn agent_events(&self, agent_id: &str) -> Vec<&SessionEvent> {
+        self.events
+            .iter()
+            .filter(|event| match event {
+                SessionEvent::Observation { agent_id: id, .. }
+                | SessionEvent::Action { agent_id: id, .. }
+                | SessionEvent::Screenshot { agent_id: id, .. } => id == agent_id,
+                SessionEvent::Llm(_) | SessionEvent::Embedding(_) => false,
+            })
+            .collect()
+    }
+}
+
+/// Stub provider that answers with the responses of a recorded session.
+///
+/// Each call is answered by the earliest unused recorded exchange with the same prompt (or
+/// the same texts, for embeddings), so agents running in parallel get their own responses
+/// whatever order they finish in. A call that matches nothing means the run has diverged
+/// from the recording (different page state, changed prompt template, ...): it is reported,
+/// and LLM calls are still answered with the earliest unused response so the replay can
+/// proceed.
+pub struct ReplayProvider {
+    exchanges: Mutex<Vec<LlmExchange>>,
+    embeddings: Mutex<Vec<EmbeddingExchange>>,
+    divergences: Mutex<Vec<String>>,
+}
+
+impl ReplayProvider {
+    pub fn new(archive: &SessionArchive) -> Self {
+        ReplayProvider {
+            exchanges: Mutex::new(archive.llm_exchanges()),
+            embeddings: Mutex::new(archive.embedding_exchanges()),
+            divergences: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Calls that didn't match the recording, in the order they were made.
+    pub fn divergences(&self) -> Vec<String> {
+        self.divergences.lock().unwrap().clone()
+    }
+
+    /// Recorded LLM and embedding calls the replay hasn't made (yet).
+    pub fn unused(&self) -> usize {
+        self.exchanges.lock().unwrap().len() + self.embeddings.lock().unwrap().len()
+    }
+
+    fn diverged(&self, divergence: String) {
+        warn!("Replay diverged: {}", divergence);
+        self.divergences.lock().unwrap().push(divergence);
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    async fn generate(&self, prompt: &str, system_instructions: &str) -> LlmResult<String> {
+        let exchange = {
+            let mut exchanges = self.exchanges.lock().unwrap();
+            let matching = exchanges.iter().position(|exchange| {
+                exchange.prompt == prompt && exchange.system_instructions == system_instructions
+            });
+            match matching {
+                Some(index) => Some(exchanges.remove(index)),
+                None if exchanges.is_empty() => None,
+                None => {
+                    let exchange = exchanges.remove(0);
+                    drop(exchanges);
+                    self.diverged(format!(
+                        "no recorded LLM call has this prompt; answered with call {}",
+                        exchange.seq
+                    ));
+                    Some(exchange)
+                }
+            }
+        };
+        let Some(exchange) = exchange else {
+            self.diverged(String::from("LLM call after the recorded ones ran out"));
+            return Err("Replay exhausted: the recorded session has no more LLM responses".into());
+        };
+
+        match (exchange.response, exchange.error) {
+            (Some(response), _) => Ok(response),
+            (None, Some(error)) => Err(error.into()),
+            (None, None) => {
+                Err(format!("Recorded LLM call {} has no response", exchange.seq).into())
+            }
+        }
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        let exchange = {
+            let mut embeddings = self.embeddings.lock().unwrap();
+            embeddings
+                .iter()
+                .position(|exchange| exchange.texts == texts)
+                .map(|index| embeddings.remove(index))
+        };
+        let Some(exchange) = exchange else {
+            self.diverged(format!(
+                "no recorded embedding call has these {} text(s)",
+                texts.len()
+            ));
+            return Err("Replay has no recorded embedding for these texts".into());
+        };
+
+        match (exchange.vectors, exchange.error) {
+            (Some(vectors), _) => Ok(vectors),
+            (None, Some(error)) => Err(error.into()),
+            (None, None) => {
+                Err(format!("Recorded embedding call {} has no vectors", exchange.seq).into())
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const EVENTS_FILE: &str = "events.jsonl";
+pub const SCREENSHOTS_DIR: &str = "screenshots";
+
+/// Top-level metadata of a recorded session archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub version: u32,
+    /// The user task the session was started with.
+    pub task: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+/// A single prompt/response pair sent through the LLM provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmExchange {
+    /// Zero-based call index within the session, shared with [`EmbeddingExchange`]s.
+    pub seq: usize,
+    pub prompt: String,
+    pub system_instructions: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A batch of texts embedded through the LLM provider, e.g. for memory retrieval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingExchange {
+    pub seq: usize,
+    pub texts: Vec<String>,
+    pub vectors: Option<Vec<Vec<f32>>>,
+    pub error: Option<String>,
+}
+
+/// One line of `events.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Llm(LlmExchange),
+    Embedding(EmbeddingExchange),
+    /// Browser state seen by an agent at the start of a step.
+    Observation {
+        agent_id: String,
+        step: usize,
+        url: String,
+        interactive_elements: BTreeMap<String, String>,
+    },
+    /// An action dispatched to the executor and the result it returned.
+    Action {
+        agent_id: String,
+        step: usize,
+        action: Value,
+        result: String,
+    },
+    /// Annotated screenshot taken after a step, relative to the archive root.
+    Screenshot {
+        agent_id: String,
+        step: usize,
+        path: String,
+    },
+}
@@ -0,0 +1,136 @@
+use crate::agent::history::now_millis;
+use crate::llm::provider::TokenUsage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// One line of a run trace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRecord {
+    /// Milliseconds since the Unix epoch.
+    pub ts: u64,
+    pub run_id: String,
+    #[serde(flatten)]
+    pub event: TraceEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    RunStarted {
+        task: String,
+    },
+    /// The plan being executed. Revision 0 is the initial plan; each re-plan adds one.
+    Plan {
+        revision: usize,
+        plan: Value,
+    },
+    PlanStepStarted {
+        index: usize,
+        id: String,
+    },
+    PlanStepFinished {
+        index: usize,
+        id: String,
+        duration_ms: u64,
+        output: Option<String>,
+        error: Option<String>,
+    },
+    AgentStep(Box<AgentStepTrace>),
+    RunFinished {
+        duration_ms: u64,
+        steps: usize,
+        failed: usize,
+    },
+}
+
+/// Everything about one iteration of an agent's loop.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentStepTrace {
+    pub agent_id: String,
+    pub step: usize,
+    pub goal: String,
+    /// FNV-1a hash of the task agent prompt, to spot identical prompts across runs.
+    pub prompt_hash: String,
+    pub prompt_chars: usize,
+    pub llm_latency_ms: Option<u64>,
+    pub usage: Option<TokenUsage>,
+    /// The parsed `next_action` call, if the response contained a valid one.
+    pub action: Option<Value>,
+    /// Why the response was rejected, if it was.
+    pub action_error: Option<String>,
+    pub result: Option<String>,
+    /// Page content read by the action, e.g. by `extract_content`. Long content is
+    /// truncated.
+    pub extracted: Option<String>,
+    pub url_before: String,
+    pub url_after: Option<String>,
+    pub screenshot: Option<String>,
+    pub error: Option<String>,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+/// Appends [`TraceRecord`]s for one run to `<dir>/<run_id>.jsonl`.
+pub struct TraceWriter {
+    run_id: String,
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl TraceWriter {
+    /// Starts a trace for a run of `task` and writes its `run_started` event.
+    pub fn create(dir: impl AsRef<Path>, task: &str) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let run_id = format!("{}-{}", now_millis(), std::process::id());
+        let path = dir.join(format!("{}.jsonl", run_id));
+        let writer = TraceWriter {
+            file: Mutex::new(File::create(&path)?),
+            run_id,
+            path,
+        };
+        writer.emit(TraceEvent::RunStarted {
+            task: task.to_string(),
+        });
+        Ok(writer)
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// `<dir>/<run_id>/`, for files that belong with the trace such as screenshots.
+    pub fn artifacts_dir(&self) -> PathBuf {
+        self.path.with_extension("")
+    }
+
+    /// Appends an event. Failures are logged rather than returned so tracing never aborts
+    /// the run.
+    pub fn emit(&self, event: TraceEvent) {
+        let record = TraceRecord {
+            ts: now_millis(),
+            run_id: self.run_id.clone(),
+            event,
+        };
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize trace event: {}", e);
+                return;
+            }
+        };
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+            warn!("Failed to write trace event: {}", e);
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use reqwest;
-use serde_json::{Value, json};
-use std::env;
+use crate::llm::image::ImagePart;
+use crate::llm::provider::{LlmResponse, LlmResult, current_provider};
 use std::io::{self, Write};
+use std::time::Instant;
+use tracing::{Instrument, Span, debug, field, info_span, warn};
 
 // General function to prompt user, get input, and return it
 pub fn get_user_input(prompt: &str) -> String {
@@ -19,61 +20,76 @@ pub fn get_user_input(prompt: &str) -> String {
     input.trim().to_string()
 }
 
-pub async fn generate_ai_response(
+/// Sends `prompt` to the currently configured [`LlmProvider`] and returns the generated text.
+///
+/// The provider is chosen from the `OCULUS_LLM_*` environment variables on first use
+/// (Gemini by default) and can be replaced with [`crate::llm::provider::set_provider`].
+///
+/// [`LlmProvider`]: crate::llm::provider::LlmProvider
+pub async fn generate_ai_response(prompt: &str, system_instructions: &str) -> LlmResult<String> {
+    generate_ai_response_with_usage(prompt, system_instructions)
+        .await
+        .map(|response| response.text)
+}
+
+/// Like [`generate_ai_response`], plus the token counts reported by the provider.
+///
+/// Each call runs in an `llm_call` span carrying the provider, prompt size, latency and
+/// token counts.
+pub async fn generate_ai_response_with_usage(
     prompt: &str,
     system_instructions: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let api_key =
-        env::var("GEMINI_API_KEY").map_err(|_| "GEMINI_API_KEY environment variable not set")?;
+) -> LlmResult<LlmResponse> {
+    generate_ai_response_with_images(prompt, &[], system_instructions).await
+}
 
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent?key={}",
-        api_key
+/// Like [`generate_ai_response_with_usage`], with images attached to the prompt.
+pub async fn generate_ai_response_with_images(
+    prompt: &str,
+    images: &[ImagePart],
+    system_instructions: &str,
+) -> LlmResult<LlmResponse> {
+    let provider = current_provider();
+    let span = info_span!(
+        "llm_call",
+        provider = provider.name(),
+        prompt_chars = prompt.len() + system_instructions.len(),
+        images = images.len(),
+        latency_ms = field::Empty,
+        prompt_tokens = field::Empty,
+        completion_tokens = field::Empty,
     );
-
-    let payload = json!({
-        "system_instruction": {
-            "parts": [
-                {
-                    "text": system_instructions
-                }
-            ]
-        },
-        "contents": [
-            {
-                "parts": [
-                    {
-                        "text": prompt
+    async move {
+        let started = Instant::now();
+        let result = provider
+            .generate_with_images(prompt, images, system_instructions)
+            .await;
+        let span = Span::current();
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        match &result {
+            Ok(response) => {
+                if let Some(usage) = &response.usage {
+                    if let Some(tokens) = usage.prompt_tokens {
+                        span.record("prompt_tokens", tokens);
                     }
-                ]
+                    if let Some(tokens) = usage.completion_tokens {
+                        span.record("completion_tokens", tokens);
+                    }
+                }
+                debug!(response_chars = response.text.len(), "LLM call finished");
             }
-        ]
-    });
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API request failed with status: {}", response.status()).into());
+            Err(e) => warn!("LLM call failed: {}", e),
+        }
+        result
     }
+    .instrument(span)
+    .await
+}
 
-    let response_json: Value = response.json().await?;
-
-    // Extract the generated text from the response
-    let generated_text = response_json
-        .get("candidates")
-        .and_then(|candidates| candidates.get(0))
-        .and_then(|candidate| candidate.get("content"))
-        .and_then(|content| content.get("parts"))
-        .and_then(|parts| parts.get(0))
-        .and_then(|part| part.get("text"))
-        .and_then(|text| text.as_str())
-        .unwrap_or("No response generated");
-
-    Ok(generated_text.to_string())
+/// 64-bit FNV-1a, stable across platforms and Rust versions. Used for content hashes
+/// that are stored or compared between runs.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
 }
@@ -0,0 +1,56 @@
+use project_oculus::browser_control::accessibility::{AxNode, render_tree};
+use serde_json::json;
+
+fn tree() -> Vec<AxNode> {
+    let cells: Vec<_> = (0..40)
+        .map(|row| json!({ "role": "row", "children": [{ "role": "cell", "name": format!("Row {} of the results table", row) }] }))
+        .collect();
+    serde_json::from_value(json!([
+        { "role": "navigation", "name": "Main", "children": [{ "role": "link", "name": "Home", "element": 1 }] },
+        { "role": "heading", "name": "Results", "level": 1 },
+        { "role": "text", "name": "Showing all results for your search" },
+        { "role": "table", "children": cells },
+        { "role": "form", "children": [
+            { "role": "textbox", "name": "Email", "element": 2, "value": "" },
+            { "role": "button", "name": "Subscribe", "element": 3 }
+        ] },
+        { "role": "navigation", "name": "Pagination", "children": [{ "role": "link", "name": "Next", "element": 4 }] }
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn renders_everything_within_budget() {
+    let outline = render_tree(&tree(), 10_000);
+    assert!(outline.starts_with("- navigation \"Main\"\n  - [1] link \"Home\""));
+    assert!(outline.contains("- text \"Showing all results for your search\""));
+    assert!(outline.contains("    - cell \"Row 39 of the results table\""));
+    assert!(!outline.contains("left out"));
+}
+
+#[test]
+fn collapses_subtrees_without_elements_before_anything_else() {
+    let outline = render_tree(&tree(), 150);
+    assert!(!outline.contains("- text"));
+    assert!(outline.contains("- table (80 nested node(s) left out)"));
+    assert!(outline.contains("- heading \"Results\" level=1"));
+    for element in ["[1] link", "[2] textbox", "[3] button", "[4] link \"Next\""] {
+        assert!(
+            outline.contains(element),
+            "{} missing from\n{}",
+            element,
+            outline
+        );
+    }
+}
+
+#[test]
+fn keeps_numbered_elements_and_their_ancestors_over_budget() {
+    let outline = render_tree(&tree(), 1);
+    assert_eq!(
+        outline,
+        "- navigation \"Main\"\n  - [1] link \"Home\"\n- form\n  - [2] textbox \"Email\" value=\"\"\n  \
+         - [3] button \"Subscribe\"\n- navigation \"Pagination\"\n  - [4] link \"Next\"\n\
+         … 82 more node(s) left out to fit the page into the prompt"
+    );
+}
@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use project_oculus::llm::provider::LlmResult;
+use project_oculus::memory::embedding::{Embedder, HashEmbedder};
+use project_oculus::memory::memory_manager::MemoryManager;
+use project_oculus::memory::retrieval::SemanticRetriever;
+use project_oculus::memory::types::{MemoryKind, MemoryQuery, MemoryRecord, domain_of};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// [`HashEmbedder`] that counts the texts it embeds and can be made to fail.
+#[derive(Default)]
+struct CountingEmbedder {
+    inner: HashEmbedder,
+    embedded: AtomicUsize,
+    fail: AtomicBool,
+}
+
+#[async_trait]
+impl Embedder for CountingEmbedder {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err("embedding endpoint unavailable".into());
+        }
+        self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+        self.inner.embed(texts).await
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("oculus-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn record(id: u64, content: &str, url: &str) -> MemoryRecord {
+    MemoryRecord {
+        id,
+        kind: MemoryKind::Fact,
+        content: content.to_string(),
+        url: Some(url.to_string()),
+        domain: domain_of(url),
+        agent_id: "test".to_string(),
+        created_at: id,
+    }
+}
+
+fn records() -> Vec<MemoryRecord> {
+    vec![
+        record(
+            0,
+            "Checkout requires a logged in account",
+            "https://shop.com/cart",
+        ),
+        record(
+            1,
+            "Monthly pricing plans are listed on the pricing page",
+            "https://a.com/pricing",
+        ),
+        record(
+            2,
+            "The cookie banner must be accepted first",
+            "https://news.com/",
+        ),
+        record(
+            3,
+            "Pricing for teams is on the plans page",
+            "https://b.com/plans",
+        ),
+    ]
+}
+
+fn query(text: &str, url: Option<&str>, limit: usize) -> MemoryQuery {
+    MemoryQuery {
+        url: url.map(str::to_string),
+        text: text.to_string(),
+        limit,
+    }
+}
+
+fn ids(records: &[MemoryRecord]) -> Vec<u64> {
+    records.iter().map(|record| record.id).collect()
+}
+
+#[tokio::test]
+async fn ranks_the_most_similar_memories_first() {
+    let dir = temp_dir("memory-top-k");
+    let retriever =
+        SemanticRetriever::open(Arc::new(HashEmbedder::default()), dir.join("index.json")).unwrap();
+
+    let found = retriever
+        .retrieve(&records(), &query("monthly pricing plans", None, 2))
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&found), vec![1, 3]);
+}
+
+#[tokio::test]
+async fn boosts_memories_about_the_current_site() {
+    let dir = temp_dir("memory-url-boost");
+    let retriever =
+        SemanticRetriever::open(Arc::new(HashEmbedder::default()), dir.join("index.json")).unwrap();
+    let records = records();
+
+    let anywhere = retriever
+        .retrieve(&records, &query("monthly pricing plans", None, 1))
+        .await
+        .unwrap();
+    let on_b = retriever
+        .retrieve(
+            &records,
+            &query("monthly pricing plans", Some("https://b.com/"), 1),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&anywhere), vec![1]);
+    assert_eq!(ids(&on_b), vec![3]);
+}
+
+#[tokio::test]
+async fn reopened_index_only_embeds_the_query() {
+    let dir = temp_dir("memory-reopen");
+    let path = dir.join("index.json");
+    let records = records();
+    let first = Arc::new(CountingEmbedder::default());
+    SemanticRetriever::open(first.clone(), &path)
+        .unwrap()
+        .retrieve(&records, &query("pricing", None, 2))
+        .await
+        .unwrap();
+    assert_eq!(first.embedded.load(Ordering::SeqCst), records.len() + 1);
+    assert!(path.exists());
+    assert!(!path.with_extension("tmp").exists());
+
+    let second = Arc::new(CountingEmbedder::default());
+    let found = SemanticRetriever::open(second.clone(), &path)
+        .unwrap()
+        .retrieve(&records, &query("pricing", None, 2))
+        .await
+        .unwrap();
+
+    assert_eq!(found.len(), 2);
+    assert_eq!(second.embedded.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn re_embeds_memories_whose_content_changed() {
+    let dir = temp_dir("memory-content-hash");
+    let embedder = Arc::new(CountingEmbedder::default());
+    let retriever = SemanticRetriever::open(embedder.clone(), dir.join("index.json")).unwrap();
+    let mut records = records();
+    retriever.sync(&records).await.unwrap();
+    embedder.embedded.store(0, Ordering::SeqCst);
+
+    records[2].content = "Newsletter signups need an email address".to_string();
+    let found = retriever
+        .retrieve(&records, &query("newsletter email signup", None, 1))
+        .await
+        .unwrap();
+
+    assert_eq!(embedder.embedded.load(Ordering::SeqCst), 2);
+    assert_eq!(ids(&found), vec![2]);
+}
+
+#[tokio::test]
+async fn falls_back_to_keywords_when_embedding_fails() {
+    let dir = temp_dir("memory-fallback");
+    let embedder = Arc::new(CountingEmbedder::default());
+    embedder.fail.store(true, Ordering::SeqCst);
+    let memory = MemoryManager::open(dir.join("memory.jsonl"))
+        .unwrap()
+        .with_retriever(SemanticRetriever::open(embedder.clone(), dir.join("index.json")).unwrap());
+    for record in records() {
+        memory.remember(
+            record.kind,
+            &record.content,
+            record.url.as_deref(),
+            &record.agent_id,
+        );
+    }
+
+    let query = query("cookie banner", None, 3);
+    let found = memory.retrieve(&query).await;
+
+    assert_eq!(found, memory.recall(&query));
+    assert_eq!(found.len(), 1);
+    assert!(found[0].content.contains("cookie banner"));
+    assert_eq!(embedder.embedded.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn leaves_out_unrelated_memories() {
+    let dir = temp_dir("memory-cutoff");
+    let retriever =
+        SemanticRetriever::open(Arc::new(HashEmbedder::default()), dir.join("index.json")).unwrap();
+    let records = records();
+
+    let found = retriever
+        .retrieve(&records, &query("cookie banner", None, 4))
+        .await
+        .unwrap();
+    let on_shop = retriever
+        .retrieve(
+            &records,
+            &query("cookie banner", Some("https://shop.com/"), 4),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(ids(&found), vec![2]);
+    let mut on_shop = ids(&on_shop);
+    on_shop.sort();
+    assert_eq!(on_shop, vec![0, 2]);
+}
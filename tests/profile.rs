@@ -0,0 +1,139 @@
+use project_oculus::browser_control::backend::StoredCookie;
+use project_oculus::browser_control::profile::{BrowserProfile, ProfileState, host_matches};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+fn profile_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("oculus-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("browser.profile")
+}
+
+fn cookie(name: &str, value: &str, domain: &str) -> StoredCookie {
+    StoredCookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        domain: Some(domain.to_string()),
+        path: Some("/".to_string()),
+        expires: None,
+        secure: true,
+        http_only: true,
+        same_site: Some("Lax".to_string()),
+    }
+}
+
+fn state() -> ProfileState {
+    ProfileState {
+        saved_at: 0,
+        cookies: vec![cookie("session", "secret", ".dashboard.example.com")],
+        local_storage: BTreeMap::from([(
+            "https://dashboard.example.com".to_string(),
+            BTreeMap::from([("token".to_string(), "abc".to_string())]),
+        )]),
+    }
+}
+
+/// Saves [`state`] to a new profile at `path`.
+fn save(path: &Path) {
+    let profile =
+        BrowserProfile::open_with_passphrase(path, "hunter2".to_string(), vec![]).unwrap();
+    profile.merge(state());
+    profile.save().unwrap();
+}
+
+fn edit_file(path: &Path, edit: impl FnOnce(&mut Value)) {
+    let mut file: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    edit(&mut file);
+    std::fs::write(path, file.to_string()).unwrap();
+}
+
+#[test]
+fn saved_profile_opens_with_the_same_passphrase() {
+    let path = profile_path("profile-round-trip");
+    save(&path);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("secret"));
+    let reopened =
+        BrowserProfile::open_with_passphrase(&path, "hunter2".to_string(), vec![]).unwrap();
+    let restored = reopened.state();
+
+    assert_eq!(restored.cookies, state().cookies);
+    assert_eq!(restored.local_storage, state().local_storage);
+    assert!(restored.saved_at > 0);
+}
+
+#[test]
+fn wrong_passphrase_is_rejected() {
+    let path = profile_path("profile-wrong-key");
+    save(&path);
+
+    let result = BrowserProfile::open_with_passphrase(&path, "hunter3".to_string(), vec![]);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn tampered_ciphertext_is_rejected() {
+    let path = profile_path("profile-tampered");
+    save(&path);
+    edit_file(&path, |file| {
+        let ciphertext = file["ciphertext"].as_str().unwrap();
+        let flipped = if ciphertext.starts_with('A') {
+            "B"
+        } else {
+            "A"
+        };
+        file["ciphertext"] = Value::String(format!("{}{}", flipped, &ciphertext[1..]));
+    });
+
+    let result = BrowserProfile::open_with_passphrase(&path, "hunter2".to_string(), vec![]);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn unexpected_iteration_count_is_rejected() {
+    let path = profile_path("profile-iterations");
+    save(&path);
+    edit_file(&path, |file| file["iterations"] = Value::from(u32::MAX));
+
+    let error = BrowserProfile::open_with_passphrase(&path, "hunter2".to_string(), vec![])
+        .err()
+        .unwrap();
+
+    assert!(error.to_string().contains("iteration count"));
+}
+
+#[test]
+fn merge_replaces_cookies_with_the_same_name_domain_and_path() {
+    let mut merged = state();
+    merged.merge(ProfileState {
+        saved_at: 0,
+        cookies: vec![
+            cookie("session", "renewed", ".dashboard.example.com"),
+            cookie("session", "other", ".example.org"),
+        ],
+        local_storage: BTreeMap::from([(
+            "https://dashboard.example.com".to_string(),
+            BTreeMap::new(),
+        )]),
+    });
+
+    let values: Vec<_> = merged
+        .cookies
+        .iter()
+        .map(|cookie| cookie.value.as_str())
+        .collect();
+    assert_eq!(values, ["renewed", "other"]);
+    assert!(merged.local_storage["https://dashboard.example.com"].is_empty());
+}
+
+#[test]
+fn host_matches_the_domain_and_its_subdomains() {
+    assert!(host_matches("example.com", "example.com"));
+    assert!(host_matches("Dashboard.Example.com", "example.com"));
+    assert!(!host_matches("badexample.com", "example.com"));
+    assert!(!host_matches("example.com.evil.net", "example.com"));
+}
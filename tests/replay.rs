@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use project_oculus::llm::provider::{LlmProvider, LlmResult};
+use project_oculus::session::recorder::{RecordingProvider, SessionRecorder};
+use project_oculus::session::replay::{ReplayProvider, SessionArchive};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Answers every prompt with itself reversed, and embeds texts by their length.
+struct EchoProvider;
+
+#[async_trait]
+impl LlmProvider for EchoProvider {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    async fn generate(&self, prompt: &str, _system_instructions: &str) -> LlmResult<String> {
+        Ok(prompt.chars().rev().collect())
+    }
+
+    async fn embed(&self, texts: &[String]) -> LlmResult<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+    }
+}
+
+fn archive_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("oculus-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Records two agents' prompts and an embedding call, then loads the archive back.
+async fn record(name: &str) -> SessionArchive {
+    let dir = archive_dir(name);
+    let recorder = Arc::new(SessionRecorder::create(&dir, "task").unwrap());
+    let provider = RecordingProvider::new(Arc::new(EchoProvider), recorder);
+    provider.generate("agent one", "system").await.unwrap();
+    provider.generate("agent two", "system").await.unwrap();
+    provider
+        .embed(&["memory".to_string(), "query".to_string()])
+        .await
+        .unwrap();
+    SessionArchive::load(&dir).unwrap()
+}
+
+#[tokio::test]
+async fn replays_responses_by_prompt_whatever_the_call_order() {
+    let archive = record("replay-order").await;
+    let replay = ReplayProvider::new(&archive);
+
+    assert_eq!(
+        replay.generate("agent two", "system").await.unwrap(),
+        "owt tnega"
+    );
+    let vectors = replay
+        .embed(&["memory".to_string(), "query".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(vectors, vec![vec![6.0], vec![5.0]]);
+    assert_eq!(
+        replay.generate("agent one", "system").await.unwrap(),
+        "eno tnega"
+    );
+
+    assert!(replay.divergences().is_empty());
+    assert_eq!(replay.unused(), 0);
+}
+
+#[tokio::test]
+async fn reports_prompts_and_embeddings_missing_from_the_recording() {
+    let archive = record("replay-diverged").await;
+    let replay = ReplayProvider::new(&archive);
+
+    // Unknown prompts still get the earliest unused response
+    assert_eq!(
+        replay.generate("agent three", "system").await.unwrap(),
+        "eno tnega"
+    );
+    assert!(replay.embed(&["other".to_string()]).await.is_err());
+
+    assert_eq!(replay.divergences().len(), 2);
+    assert_eq!(replay.unused(), 2);
+}
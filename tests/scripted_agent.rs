@@ -0,0 +1,375 @@
+use async_trait::async_trait;
+use project_oculus::agent::action::{ActionCall, ActionError};
+use project_oculus::agent::agent::AIAgent;
+use project_oculus::agent::planner::planner_agent;
+use project_oculus::agent::registry::{ActionContext, ActionHandler, ActionRegistry, ActionResult};
+use project_oculus::browser_control::backend::{
+    BrowserBackend, BrowserResult, Locator, StoredCookie,
+};
+use project_oculus::llm::provider::set_provider;
+use project_oculus::llm::scripted::ScriptedProvider;
+use serde_json::{Value, json};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The LLM provider is process-wide, so tests that install one run one at a time.
+static PROVIDER: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A page without interactive elements that records where it was sent.
+#[derive(Default)]
+struct FakeBrowser {
+    visited: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl BrowserBackend for FakeBrowser {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    async fn goto(&self, url: &str) -> BrowserResult<()> {
+        self.visited.lock().unwrap().push(url.to_string());
+        Ok(())
+    }
+
+    async fn back(&self) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn current_url(&self) -> BrowserResult<String> {
+        Ok(self
+            .visited
+            .lock()
+            .unwrap()
+            .last()
+            .cloned()
+            .unwrap_or_else(|| "about:blank".to_string()))
+    }
+
+    async fn click(&self, _locator: &Locator) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn type_text(&self, _locator: &Locator, _text: &str) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn execute_script(&self, _script: &str, _args: Vec<Value>) -> BrowserResult<Value> {
+        Ok(json!([]))
+    }
+
+    async fn screenshot_png(&self) -> BrowserResult<Vec<u8>> {
+        Err("no screenshots".into())
+    }
+
+    async fn new_tab(&self) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn tab_count(&self) -> BrowserResult<usize> {
+        Ok(1)
+    }
+
+    async fn switch_tab(&self, _index: usize) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn close_tab(&self) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn quit(&self) -> BrowserResult<()> {
+        Ok(())
+    }
+
+    async fn cookies(&self) -> BrowserResult<Vec<StoredCookie>> {
+        Ok(Vec::new())
+    }
+
+    async fn add_cookies(&self, _cookies: &[StoredCookie]) -> BrowserResult<()> {
+        Ok(())
+    }
+}
+
+fn install(responses: Vec<Value>) -> Arc<ScriptedProvider> {
+    let provider = Arc::new(ScriptedProvider::new(
+        responses.into_iter().map(|response| response.to_string()),
+    ));
+    set_provider(provider.clone());
+    provider
+}
+
+fn plan(tools: &str) -> Value {
+    json!({
+        "steps": [
+            {
+                "id": "search",
+                "parameters": {
+                    "goal": "Find the price",
+                    "role": "Researcher",
+                    "backstory": "Careful",
+                    "description": "Looks up prices",
+                    "tools": tools
+                },
+                "run_in_parallel": false,
+                "needs_context_from": []
+            }
+        ]
+    })
+}
+
+fn agent(browser: Arc<FakeBrowser>) -> AIAgent {
+    AIAgent::new(
+        "search".to_string(),
+        "Find the price".to_string(),
+        "Looks up prices".to_string(),
+        "go_to_url, stop".to_string(),
+        "Researcher".to_string(),
+        "Careful".to_string(),
+        String::new(),
+        browser,
+    )
+    .with_settle_delay(Duration::ZERO)
+    .with_step_delay(Duration::ZERO)
+}
+
+fn call(name: &str, params: Value) -> ActionCall {
+    ActionCall {
+        name: name.to_string(),
+        params,
+    }
+}
+
+#[tokio::test]
+async fn planner_returns_a_valid_plan() {
+    let _guard = PROVIDER.lock().await;
+    let provider = install(vec![plan("go_to_url, extract_content")]);
+
+    let result = planner_agent("Find the price", &["go_to_url", "extract_content"], 2)
+        .await
+        .unwrap();
+
+    assert_eq!(result.steps.len(), 1);
+    assert_eq!(result.steps[0].id, "search");
+    let prompts = provider.recorded_prompts();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].prompt.contains("Find the price"));
+}
+
+#[tokio::test]
+async fn planner_repairs_an_invalid_plan() {
+    let _guard = PROVIDER.lock().await;
+    let provider = install(vec![plan("teleport"), plan("go_to_url")]);
+
+    let result = planner_agent("Find the price", &["go_to_url"], 2)
+        .await
+        .unwrap();
+
+    assert_eq!(result.steps[0].param("tools"), "go_to_url");
+    let prompts = provider.recorded_prompts();
+    assert_eq!(prompts.len(), 2);
+    assert!(
+        prompts[1]
+            .prompt
+            .contains("It was rejected for these reasons")
+    );
+    assert!(prompts[1].prompt.contains("unknown tool(s): teleport"));
+}
+
+#[tokio::test]
+async fn planner_gives_up_after_the_repair_attempts() {
+    let _guard = PROVIDER.lock().await;
+    let provider = install(vec![json!("not a plan"), plan("teleport")]);
+
+    let error = planner_agent("Find the price", &["go_to_url"], 1)
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("after 1 repair attempt(s)"));
+    assert_eq!(provider.remaining(), 0);
+}
+
+#[test]
+fn registry_rejects_unknown_actions_and_bad_parameters() {
+    let registry = ActionRegistry::with_builtins();
+
+    assert!(matches!(
+        registry.validate(&call("teleport", json!({}))),
+        Err(ActionError::UnknownAction(_))
+    ));
+    assert!(matches!(
+        registry.validate(&call("go_to_url", json!({}))),
+        Err(ActionError::InvalidParameters { .. })
+    ));
+    assert!(matches!(
+        registry.validate(&call("go_to_url", json!({ "url": "ftp://example.com" }))),
+        Err(ActionError::InvalidParameters { .. })
+    ));
+    for filename in ["../notes", "/etc/passwd", "C:notes", "a\\b"] {
+        let params = json!({ "filename": filename, "content": "", "format": "txt" });
+        assert!(
+            matches!(
+                registry.validate(&call("create_document", params)),
+                Err(ActionError::InvalidParameters { .. })
+            ),
+            "{} was accepted",
+            filename
+        );
+    }
+    assert!(
+        registry
+            .validate(&call("go_to_url", json!({ "url": "https://example.com" })))
+            .is_ok()
+    );
+}
+
+struct Echo;
+
+#[async_trait]
+impl ActionHandler for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn description(&self) -> &str {
+        "Return the given text."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "text": { "type": "string" } },
+            "required": ["text"],
+            "additionalProperties": false
+        })
+    }
+
+    async fn execute(&self, _ctx: &ActionContext<'_>, params: &Value) -> ActionResult {
+        Ok(format!(
+            "FINAL_ANSWER: {}",
+            params["text"].as_str().unwrap()
+        ))
+    }
+}
+
+#[tokio::test]
+async fn registry_dispatches_builtin_and_custom_actions() {
+    let browser = FakeBrowser::default();
+    let mut registry = ActionRegistry::with_builtins();
+    registry.register(Arc::new(Echo));
+    let ctx = ActionContext {
+        browser: &browser,
+        agent_id: "test",
+        elements: &[],
+        output_dir: Path::new("documents"),
+        extracted: Mutex::new(None),
+    };
+
+    let dispatch = |name: &str, params: Value| {
+        let call = call(name, params);
+        let registry = &registry;
+        let ctx = &ctx;
+        async move { registry.dispatch(ctx, &call).await.unwrap() }
+    };
+    assert_eq!(
+        dispatch("go_to_url", json!({ "url": "https://example.com" })).await,
+        "CONTINUE"
+    );
+    assert_eq!(
+        browser.visited.lock().unwrap().as_slice(),
+        ["https://example.com"]
+    );
+    assert_eq!(dispatch("done", json!({})).await, "AGENT_DONE");
+    assert_eq!(
+        dispatch("final_answer", json!({ "answer": "42" })).await,
+        "FINAL_ANSWER: 42"
+    );
+    assert_eq!(
+        dispatch("stop", json!({ "final_answer": "42" })).await,
+        "FINAL_ANSWER: 42"
+    );
+    assert_eq!(
+        dispatch("echo", json!({ "text": "hi" })).await,
+        "FINAL_ANSWER: hi"
+    );
+    assert!(
+        dispatch("echo", json!({}))
+            .await
+            .starts_with("ERROR_INVALID_ACTION: invalid parameters for `echo`")
+    );
+}
+
+#[tokio::test]
+async fn agent_stops_with_the_final_answer_after_an_invalid_action() {
+    let _guard = PROVIDER.lock().await;
+    let provider = install(vec![
+        json!({ "next_action": { "teleport": {} } }),
+        json!({ "next_action": { "go_to_url": { "url": "https://example.com" } } }),
+        json!({ "next_action": { "stop": { "final_answer": "42 USD" } } }),
+    ]);
+    let browser = Arc::new(FakeBrowser::default());
+
+    let answer = agent(browser.clone()).process().await.unwrap();
+
+    assert_eq!(answer, "42 USD");
+    assert_eq!(provider.remaining(), 0);
+    assert_eq!(
+        browser.visited.lock().unwrap().as_slice(),
+        ["https://example.com"]
+    );
+    let prompts = provider.recorded_prompts();
+    assert!(prompts[1].prompt.contains("The chosen action was rejected"));
+}
+
+#[tokio::test]
+async fn agent_finishes_on_done() {
+    let _guard = PROVIDER.lock().await;
+    let provider = install(vec![json!({ "next_action": { "done": {} } })]);
+
+    let result = agent(Arc::new(FakeBrowser::default())).process().await;
+
+    assert!(result.is_ok());
+    assert_eq!(provider.recorded_prompts().len(), 1);
+}
+
+#[tokio::test]
+async fn agent_fails_when_it_runs_out_of_steps() {
+    let _guard = PROVIDER.lock().await;
+    install(vec![json!("not json"), json!("still not json")]);
+
+    let error = agent(Arc::new(FakeBrowser::default()))
+        .with_max_steps(2)
+        .process()
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("Reached max steps (2)"));
+}
+
+#[tokio::test]
+async fn agent_finishes_on_stop_without_an_answer() {
+    let _guard = PROVIDER.lock().await;
+    let provider = install(vec![json!({ "next_action": { "stop": {} } })]);
+
+    let result = agent(Arc::new(FakeBrowser::default()))
+        .with_max_steps(2)
+        .process()
+        .await;
+
+    assert!(result.is_ok());
+    assert_eq!(provider.recorded_prompts().len(), 1);
+}
+
+#[tokio::test]
+async fn agent_fails_when_the_provider_fails() {
+    let _guard = PROVIDER.lock().await;
+    install(vec![]);
+
+    let error = agent(Arc::new(FakeBrowser::default()))
+        .process()
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().starts_with("Error in task agent"));
+}